@@ -41,6 +41,22 @@ pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *m
                 println!("{:p}", a);
                 return a;
             }
+            "glXDestroyContext" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_glx_destroy_context\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "glXSwapBuffersMscOML" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_glx_swap_buffers_msc_oml\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
             "eglSwapBuffers" => {
                 let a = real_dlsym(
                     libc::RTLD_NEXT,
@@ -49,6 +65,152 @@ pub unsafe extern "C" fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *m
                 println!("{:p}", a);
                 return a;
             }
+            "eglSwapBuffersWithDamageKHR" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_egl_swap_buffers_with_damage_khr\0" as *const [u8] as *const [i8]
+                        as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "eglSwapBuffersWithDamageEXT" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_egl_swap_buffers_with_damage_ext\0" as *const [u8] as *const [i8]
+                        as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "eglGetProcAddress" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_egl_get_proc_address\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "eglDestroyContext" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_egl_destroy_context\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XOpenDisplay" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_open_display\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XCloseDisplay" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_close_display\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XSetEventQueueOwner" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_set_event_queue_owner\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XNextEvent" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_next_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XPeekEvent" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_peek_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XCheckMaskEvent" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_check_mask_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XPending" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_pending\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "XEventsQueued" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_x_events_queued\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "xcb_wait_for_event" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_xcb_wait_for_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "xcb_poll_for_event" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_xcb_poll_for_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "xcb_poll_for_queued_event" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_xcb_poll_for_queued_event\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "wl_proxy_add_listener" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_wl_proxy_add_listener\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "wl_proxy_add_dispatcher" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_wl_proxy_add_dispatcher\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
+            "wl_proxy_destroy" => {
+                let a = real_dlsym(
+                    libc::RTLD_NEXT,
+                    b"overlib_wl_proxy_destroy\0" as *const [u8] as *const [i8] as _,
+                );
+                println!("{:p}", a);
+                return a;
+            }
             _ => {}
         }
     }
@@ -0,0 +1,32 @@
+// Functions overlib exports for the application it is loaded into, to show its
+// own values in the overlay's metrics widget.
+//
+// Look them up at runtime so the application still runs without overlib:
+//
+//   #include <dlfcn.h>
+//
+//   static overlib_push_metric_fn push_metric;
+//   push_metric = (overlib_push_metric_fn)dlsym(RTLD_DEFAULT,
+//                                               "overlib_push_metric");
+//   if (push_metric)
+//     push_metric("entities", entity_count);
+//
+// They can be called from any thread. Strings are UTF-8 and copied.
+
+#ifndef OVERLIB_H
+#define OVERLIB_H
+
+// Adds value to the graph of the metric called name, NaN and infinities are
+// ignored.
+void overlib_push_metric(const char *name, double value);
+typedef void (*overlib_push_metric_fn)(const char *name, double value);
+
+// Shows text next to key, replacing what it had. A null text removes it.
+void overlib_push_text(const char *key, const char *text);
+typedef void (*overlib_push_text_fn)(const char *key, const char *text);
+
+// Lists label with the time it happened for a few seconds.
+void overlib_mark_event(const char *label);
+typedef void (*overlib_mark_event_fn)(const char *label);
+
+#endif
@@ -0,0 +1,67 @@
+// Widget plugins for overlib.
+//
+// A plugin is a shared object in $OVERLIB_PLUGIN_DIR, or in overlib/plugins in
+// the XDG data directory, exporting `overlib_plugin`. It is drawn like the
+// built-in widgets, so the `widgets` setting enables and orders it by name.
+//
+// Plugins run inside the application: a plugin returning an error, built for
+// another ABI version or failing to initialize is disabled, but one that
+// crashes takes the application down with it. Plugins can't be named hud,
+// benchmark or metrics, like the built-in widgets.
+
+#ifndef OVERLIB_PLUGIN_H
+#define OVERLIB_PLUGIN_H
+
+#include <stdint.h>
+
+#define OVERLIB_PLUGIN_ABI_VERSION 1
+
+typedef struct OverlibUi OverlibUi;
+
+typedef struct OverlibFrame {
+  double frame_time_ms;
+  double fps;
+  // whether the overlay takes input, widgets can only be clicked then
+  int interactive;
+} OverlibFrame;
+
+// Immediate-mode functions to draw with, each maps to the egui widget of the
+// same name. Strings are UTF-8 and only read during the call.
+typedef struct OverlibApi {
+  uint32_t abi_version;
+  void (*label)(OverlibUi *ui, const char *text);
+  // rgba is 0xRRGGBBAA
+  void (*colored_label)(OverlibUi *ui, uint32_t rgba, const char *text);
+  void (*heading)(OverlibUi *ui, const char *text);
+  // return 1 when clicked or changed
+  int (*button)(OverlibUi *ui, const char *text);
+  int (*checkbox)(OverlibUi *ui, const char *text, int *value);
+  int (*slider)(OverlibUi *ui, const char *text, float *value, float min,
+                float max);
+  // text may be null
+  void (*progress_bar)(OverlibUi *ui, float progress, const char *text);
+  void (*separator)(OverlibUi *ui);
+  // calls contents with a ui laying its widgets out left to right
+  void (*horizontal)(OverlibUi *ui,
+                     void (*contents)(void *data, OverlibUi *ui), void *data);
+} OverlibApi;
+
+typedef struct OverlibPlugin {
+  // OVERLIB_PLUGIN_ABI_VERSION
+  uint32_t abi_version;
+  // also the title of the plugin's window
+  const char *name;
+  // returns the data given to the other functions, null if the plugin can't
+  // run; api stays valid until shutdown
+  void *(*init)(const OverlibApi *api);
+  // draws into the plugin's window once per frame, returning non-zero
+  // disables the plugin
+  int (*draw)(void *data, OverlibUi *ui, const OverlibFrame *frame);
+  // may be null, called when the plugin is disabled or the application exits
+  void (*shutdown)(void *data);
+} OverlibPlugin;
+
+// The one symbol a plugin exports.
+const OverlibPlugin *overlib_plugin(void);
+
+#endif
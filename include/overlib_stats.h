@@ -0,0 +1,76 @@
+// Frame statistics overlib publishes in shared memory when shm_stats=1 is set,
+// for monitoring tools to read without talking to the application.
+//
+// Each process has a segment called /overlib-<pid>, i.e. /dev/shm/overlib-<pid>,
+// removed when it exits, its forked children neither write nor remove it. One
+// left by a process that crashed stays until the next process with its pid
+// replaces it, check that pid is still running.
+//
+// The fields after seq are written once per frame. Read them with
+// overlib_stats_read, which retries while the application is writing them.
+
+#ifndef OVERLIB_STATS_H
+#define OVERLIB_STATS_H
+
+#include <stdint.h>
+#include <string.h>
+
+// "OVLB" in memory order, written once the rest of the header is.
+#define OVERLIB_STATS_MAGIC 0x424c564fu
+// Fields are only ever added at the end of the structure, along with a new
+// version. Check size before reading them.
+#define OVERLIB_STATS_VERSION 1
+
+// Bits of flags.
+#define OVERLIB_STATS_HAS_GPU_TIME (1u << 0)
+
+typedef struct OverlibStats {
+  uint32_t magic;
+  uint32_t version;
+  // sizeof the structure the application writes
+  uint32_t size;
+  uint32_t pid;
+  // odd while the application writes the fields below
+  uint32_t seq;
+  // OVERLIB_STATS_HAS_* bits of the optional fields that are measured
+  uint32_t flags;
+  // frames presented since overlib was loaded
+  uint64_t frame_count;
+  uint64_t frame_time_ns;
+  // average over the last second
+  double fps;
+  // GPU time of the frame when OVERLIB_STATS_HAS_GPU_TIME is set in flags. It
+  // never is for now: overlib only sees presents, and the time between them on
+  // the GPU's timeline includes the time the GPU waited for the application
+  uint64_t gpu_time_ns;
+  // of the drawable that presented the frame
+  uint32_t width;
+  uint32_t height;
+  // "glx", "egl" or "vulkan", nul terminated
+  char frontend[16];
+} OverlibStats;
+
+// Copies a consistent snapshot of the segment mapped at shared into out.
+// Returns 0 when the segment isn't one of a version this header knows, or when
+// it stays half written, which happens when the application crashed writing it.
+static inline int overlib_stats_read(const volatile OverlibStats *shared,
+                                     OverlibStats *out) {
+  if (__atomic_load_n(&shared->magic, __ATOMIC_ACQUIRE) != OVERLIB_STATS_MAGIC ||
+      shared->version != OVERLIB_STATS_VERSION ||
+      shared->size < sizeof(OverlibStats))
+    return 0;
+  for (int tries = 0; tries < 100000; tries++) {
+    uint32_t seq = __atomic_load_n(&shared->seq, __ATOMIC_ACQUIRE);
+    if (seq & 1)
+      continue;
+    memcpy(out, (const void *)shared, sizeof(OverlibStats));
+    __atomic_thread_fence(__ATOMIC_ACQUIRE);
+    if (__atomic_load_n(&shared->seq, __ATOMIC_RELAXED) == seq) {
+      out->seq = seq;
+      return 1;
+    }
+  }
+  return 0;
+}
+
+#endif
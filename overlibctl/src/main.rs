@@ -0,0 +1,126 @@
+//! Sends a command to the control socket of a program running with overlib, see
+//! `src/control.rs`. The program needs `control=1` in its configuration.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::exit;
+
+use serde_json::{json, Value};
+
+const USAGE: &str = "usage: overlibctl [--pid PID | --socket PATH] COMMAND [ARGS]
+
+commands:
+    show | hide
+    start_logging | stop_logging
+    set KEY VALUE
+    push_metric NAME VALUE
+    push_text KEY [TEXT]        without TEXT, removes KEY
+    mark_event LABEL
+    fps
+    raw JSON                    sends a request as it is
+    <action>                    a hotkey action, e.g. toggle_overlay or screenshot
+
+without --pid or --socket, the only program listening is used";
+
+/// Where programs put their socket, see `control.rs`.
+fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/tmp/overlib-{}", unsafe { libc::geteuid() })))
+}
+
+/// The socket of the only program listening, there is no telling which one was meant
+/// otherwise. Sockets left by programs that crashed are skipped.
+fn find_socket() -> Result<PathBuf, String> {
+    let dir = runtime_dir();
+    let entries =
+        std::fs::read_dir(&dir).map_err(|e| format!("could not read {}: {}", dir.display(), e))?;
+    let mut sockets: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("overlib-") && n.ends_with(".sock"))
+        })
+        .filter(|p| UnixStream::connect(p).is_ok())
+        .collect();
+    match sockets.len() {
+        0 => Err(format!("no overlib socket in {}", dir.display())),
+        1 => Ok(sockets.remove(0)),
+        _ => Err("several programs are listening, choose one with --pid".to_string()),
+    }
+}
+
+fn request(cmd: &str, args: &[String]) -> Result<Value, String> {
+    let arg = |i: usize, name: &str| {
+        args.get(i)
+            .cloned()
+            .ok_or_else(|| format!("{} needs {}", cmd, name))
+    };
+    Ok(match cmd {
+        "raw" => serde_json::from_str(&arg(0, "JSON")?).map_err(|e| e.to_string())?,
+        "set" => json!({ "cmd": cmd, "key": arg(0, "KEY")?, "value": arg(1, "VALUE")? }),
+        "push_metric" => {
+            let value: f64 = arg(1, "VALUE")?
+                .parse()
+                .map_err(|_| "VALUE must be a number".to_string())?;
+            json!({ "cmd": cmd, "name": arg(0, "NAME")?, "value": value })
+        }
+        "push_text" => json!({ "cmd": cmd, "key": arg(0, "KEY")?, "text": args.get(1) }),
+        "mark_event" => json!({ "cmd": cmd, "label": arg(0, "LABEL")? }),
+        _ => json!({ "cmd": cmd }),
+    })
+}
+
+fn run() -> Result<bool, String> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut socket = None;
+    while let Some(option) = args.first().filter(|a| a.starts_with("--")).cloned() {
+        args.remove(0);
+        if option == "--help" {
+            println!("{}", USAGE);
+            return Ok(true);
+        }
+        if args.is_empty() {
+            return Err(format!("{} needs a value", option));
+        }
+        let value = args.remove(0);
+        socket = Some(match option.as_str() {
+            "--pid" => runtime_dir().join(format!("overlib-{}.sock", value)),
+            "--socket" => PathBuf::from(value),
+            _ => return Err(format!("unknown option {}\n{}", option, USAGE)),
+        });
+    }
+    if args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let request = request(&args[0], &args[1..])?;
+    let socket = match socket {
+        Some(socket) => socket,
+        None => find_socket()?,
+    };
+    let mut stream = UnixStream::connect(&socket)
+        .map_err(|e| format!("could not connect to {}: {}", socket.display(), e))?;
+    writeln!(stream, "{}", request).map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    let reply: Value = serde_json::from_str(&reply).map_err(|e| format!("bad reply: {}", e))?;
+    println!("{}", reply);
+    Ok(reply["ok"] == true)
+}
+
+fn main() {
+    match run() {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    }
+}
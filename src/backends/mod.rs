@@ -1 +1,2 @@
 pub mod opengl;
+pub mod vulkan;
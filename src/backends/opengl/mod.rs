@@ -1 +1,3 @@
 pub mod painter;
+pub mod screenshot;
+pub mod state;
@@ -414,10 +414,15 @@ impl Painter {
         for i in f {
             match i {
                 egui::TextureId::User(id) => {
-                    self.user_textures.remove(&id);
+                    if let Some(texture) = self.user_textures.remove(&id).and_then(|t| t.texture) {
+                        unsafe { gl::DeleteTextures(1, &texture) };
+                    }
                 }
                 egui::TextureId::Managed(id) => {
-                    self.managed_textures.remove(&id);
+                    if let Some(texture) = self.managed_textures.remove(&id).and_then(|t| t.texture)
+                    {
+                        unsafe { gl::DeleteTextures(1, &texture) };
+                    }
                 }
             }
         }
@@ -460,12 +465,14 @@ impl Painter {
                         to_update.pixels = out;
                     } else {
                         //let id = egui::TextureId::User(self.user_textures.len() as u64);
+                        // a full update reuses the texture object instead of leaking it
+                        let texture = self.user_textures.remove(id).and_then(|t| t.texture);
                         self.user_textures.insert(
                             *id,
                             UserTexture {
                                 size: (size[0], size[1]),
                                 pixels: upixels,
-                                texture: None,
+                                texture,
                                 filtering: true,
                                 dirty: true,
                             },
@@ -499,12 +506,14 @@ impl Painter {
                         out.append(&mut to_update.pixels[to * 4..].to_vec());
                         to_update.pixels = out;
                     } else {
+                        // a full update reuses the texture object instead of leaking it
+                        let texture = self.managed_textures.remove(id).and_then(|t| t.texture);
                         self.managed_textures.insert(
                             *id,
                             UserTexture {
                                 size: (size[0], size[1]),
                                 pixels: upixels,
-                                texture: None,
+                                texture,
                                 filtering: true,
                                 dirty: true,
                             },
@@ -583,7 +592,6 @@ impl Painter {
                     );
 
                     self.paint_mesh(&mesh);
-                    gl::Disable(gl::SCISSOR_TEST);
                 } else {
                     eprintln!("Primitive callbacks are currently not implemented. ");
                     todo!()
@@ -603,6 +611,15 @@ impl Painter {
             gl::DeleteBuffers(1, &self.color_buffer);
             gl::DeleteBuffers(1, &self.index_buffer);
             gl::DeleteVertexArrays(1, &self.vertex_array);
+            for texture in self
+                .user_textures
+                .values()
+                .chain(self.managed_textures.values())
+            {
+                if let Some(texture) = texture.texture {
+                    gl::DeleteTextures(1, &texture);
+                }
+            }
         }
     }
 
@@ -0,0 +1,51 @@
+use glad_gl::gl;
+
+unsafe fn get_integer(pname: gl::GLenum) -> gl::GLint {
+    let mut value = 0;
+    gl::GetIntegerv(pname, &mut value);
+    value
+}
+
+/// Reads the back buffer of the current drawable as RGB rows, bottom row first.
+///
+/// Must be called with the context the overlay is drawn in current, the read
+/// state it changes is restored afterwards.
+pub unsafe fn read_back_buffer(width: u32, height: u32) -> Vec<u8> {
+    let read_framebuffer = get_integer(gl::READ_FRAMEBUFFER_BINDING);
+    let pixel_pack_buffer = get_integer(gl::PIXEL_PACK_BUFFER_BINDING);
+    let pack_alignment = get_integer(gl::PACK_ALIGNMENT);
+    let pack_row_length = get_integer(gl::PACK_ROW_LENGTH);
+    let pack_skip_pixels = get_integer(gl::PACK_SKIP_PIXELS);
+    let pack_skip_rows = get_integer(gl::PACK_SKIP_ROWS);
+
+    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    // the read buffer is per framebuffer, only query it once the default one is bound
+    let read_buffer = get_integer(gl::READ_BUFFER);
+    gl::ReadBuffer(gl::BACK);
+    gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+    gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+    gl::PixelStorei(gl::PACK_ROW_LENGTH, 0);
+    gl::PixelStorei(gl::PACK_SKIP_PIXELS, 0);
+    gl::PixelStorei(gl::PACK_SKIP_ROWS, 0);
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 3];
+    gl::ReadPixels(
+        0,
+        0,
+        width as gl::GLsizei,
+        height as gl::GLsizei,
+        gl::RGB,
+        gl::UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as *mut _,
+    );
+
+    gl::ReadBuffer(read_buffer as gl::GLenum);
+    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_framebuffer as gl::GLuint);
+    gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pixel_pack_buffer as gl::GLuint);
+    gl::PixelStorei(gl::PACK_ALIGNMENT, pack_alignment);
+    gl::PixelStorei(gl::PACK_ROW_LENGTH, pack_row_length);
+    gl::PixelStorei(gl::PACK_SKIP_PIXELS, pack_skip_pixels);
+    gl::PixelStorei(gl::PACK_SKIP_ROWS, pack_skip_rows);
+
+    pixels
+}
@@ -0,0 +1,215 @@
+use glad_gl::gl;
+
+/// Capabilities the painter needs in a given state, along with that state.
+const REQUIRED_CAPABILITIES: [(gl::GLenum, bool); 5] = [
+    (gl::DEPTH_TEST, false),
+    (gl::STENCIL_TEST, false),
+    (gl::CULL_FACE, false),
+    (gl::SCISSOR_TEST, true),
+    (gl::BLEND, true),
+];
+
+/// Snapshot of every piece of GL state that `Painter::paint_jobs` touches.
+///
+/// Creating the guard records the application's state and puts the pipeline in
+/// the state the painter expects, dropping it restores what the application had.
+pub struct GlStateGuard {
+    capabilities: [(gl::GLenum, bool); 5],
+    /// The state only desktop OpenGL has, `None` with OpenGL ES.
+    desktop: Option<DesktopState>,
+    program: gl::GLint,
+    vertex_array: gl::GLint,
+    array_buffer: gl::GLint,
+    element_array_buffer: gl::GLint,
+    pixel_unpack_buffer: gl::GLint,
+    draw_framebuffer: gl::GLint,
+    active_texture: gl::GLint,
+    texture_2d: gl::GLint,
+    sampler: gl::GLint,
+    blend_src_rgb: gl::GLint,
+    blend_dst_rgb: gl::GLint,
+    blend_src_alpha: gl::GLint,
+    blend_dst_alpha: gl::GLint,
+    blend_equation_rgb: gl::GLint,
+    blend_equation_alpha: gl::GLint,
+    viewport: [gl::GLint; 4],
+    scissor_box: [gl::GLint; 4],
+    unpack_alignment: gl::GLint,
+    unpack_row_length: gl::GLint,
+    unpack_skip_pixels: gl::GLint,
+    unpack_skip_rows: gl::GLint,
+    color_mask: [gl::GLboolean; 4],
+}
+
+struct DesktopState {
+    framebuffer_srgb: bool,
+    polygon_mode: [gl::GLint; 2],
+}
+
+/// Whether the current context is OpenGL ES, whose `GL_VERSION` starts with "OpenGL ES".
+unsafe fn is_gles() -> bool {
+    let version = gl::GetString(gl::VERSION);
+    !version.is_null()
+        && std::ffi::CStr::from_ptr(version as *const _)
+            .to_bytes()
+            .starts_with(b"OpenGL ES")
+}
+
+unsafe fn get_integer(pname: gl::GLenum) -> gl::GLint {
+    let mut value = 0;
+    gl::GetIntegerv(pname, &mut value);
+    value
+}
+
+impl GlStateGuard {
+    /// Must be called with the context the overlay is drawn in current.
+    pub unsafe fn capture() -> Self {
+        let mut capabilities = REQUIRED_CAPABILITIES;
+        for (capability, state) in capabilities.iter_mut() {
+            *state = gl::IsEnabled(*capability) != 0;
+        }
+
+        // texture bindings are per unit, and the painter only ever uses the first one
+        let active_texture = get_integer(gl::ACTIVE_TEXTURE);
+        gl::ActiveTexture(gl::TEXTURE0);
+
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        let mut scissor_box = [0; 4];
+        gl::GetIntegerv(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+        // GL_FRAMEBUFFER_SRGB and glPolygonMode don't exist there, touching them
+        // would leave errors for the application to find
+        let desktop = if is_gles() {
+            None
+        } else {
+            let mut polygon_mode = [gl::FILL as gl::GLint; 2];
+            gl::GetIntegerv(gl::POLYGON_MODE, polygon_mode.as_mut_ptr());
+            Some(DesktopState {
+                framebuffer_srgb: gl::IsEnabled(gl::FRAMEBUFFER_SRGB) != 0,
+                polygon_mode,
+            })
+        };
+        let mut color_mask = [gl::TRUE; 4];
+        gl::GetBooleanv(gl::COLOR_WRITEMASK, color_mask.as_mut_ptr());
+
+        let guard = GlStateGuard {
+            capabilities,
+            desktop,
+            program: get_integer(gl::CURRENT_PROGRAM),
+            vertex_array: get_integer(gl::VERTEX_ARRAY_BINDING),
+            array_buffer: get_integer(gl::ARRAY_BUFFER_BINDING),
+            element_array_buffer: get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING),
+            pixel_unpack_buffer: get_integer(gl::PIXEL_UNPACK_BUFFER_BINDING),
+            draw_framebuffer: get_integer(gl::DRAW_FRAMEBUFFER_BINDING),
+            active_texture,
+            texture_2d: get_integer(gl::TEXTURE_BINDING_2D),
+            sampler: get_integer(gl::SAMPLER_BINDING),
+            blend_src_rgb: get_integer(gl::BLEND_SRC_RGB),
+            blend_dst_rgb: get_integer(gl::BLEND_DST_RGB),
+            blend_src_alpha: get_integer(gl::BLEND_SRC_ALPHA),
+            blend_dst_alpha: get_integer(gl::BLEND_DST_ALPHA),
+            blend_equation_rgb: get_integer(gl::BLEND_EQUATION_RGB),
+            blend_equation_alpha: get_integer(gl::BLEND_EQUATION_ALPHA),
+            viewport,
+            scissor_box,
+            unpack_alignment: get_integer(gl::UNPACK_ALIGNMENT),
+            unpack_row_length: get_integer(gl::UNPACK_ROW_LENGTH),
+            unpack_skip_pixels: get_integer(gl::UNPACK_SKIP_PIXELS),
+            unpack_skip_rows: get_integer(gl::UNPACK_SKIP_ROWS),
+            color_mask,
+        };
+
+        for (capability, state) in REQUIRED_CAPABILITIES {
+            (if state { gl::Enable } else { gl::Disable })(capability);
+        }
+        // the overlay goes on top of the back buffer that is about to be presented
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+        gl::BindSampler(0, 0);
+        // a bound PBO would make texture uploads read from it
+        gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+        gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, 0);
+        gl::PixelStorei(gl::UNPACK_SKIP_ROWS, 0);
+        gl::BlendEquation(gl::FUNC_ADD);
+        if guard.desktop.is_some() {
+            gl::Enable(gl::FRAMEBUFFER_SRGB);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+        }
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+
+        guard
+    }
+}
+
+impl Drop for GlStateGuard {
+    fn drop(&mut self) {
+        unsafe {
+            for (capability, state) in self.capabilities {
+                (if state { gl::Enable } else { gl::Disable })(capability);
+            }
+
+            gl::UseProgram(self.program as gl::GLuint);
+            // the element array buffer binding belongs to the vertex array
+            gl::BindVertexArray(self.vertex_array as gl::GLuint);
+            gl::BindBuffer(
+                gl::ELEMENT_ARRAY_BUFFER,
+                self.element_array_buffer as gl::GLuint,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer as gl::GLuint);
+            gl::BindBuffer(
+                gl::PIXEL_UNPACK_BUFFER,
+                self.pixel_unpack_buffer as gl::GLuint,
+            );
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.draw_framebuffer as gl::GLuint);
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_2d as gl::GLuint);
+            gl::BindSampler(0, self.sampler as gl::GLuint);
+            gl::ActiveTexture(self.active_texture as gl::GLenum);
+
+            gl::BlendFuncSeparate(
+                self.blend_src_rgb as gl::GLenum,
+                self.blend_dst_rgb as gl::GLenum,
+                self.blend_src_alpha as gl::GLenum,
+                self.blend_dst_alpha as gl::GLenum,
+            );
+            gl::BlendEquationSeparate(
+                self.blend_equation_rgb as gl::GLenum,
+                self.blend_equation_alpha as gl::GLenum,
+            );
+
+            gl::Viewport(
+                self.viewport[0],
+                self.viewport[1],
+                self.viewport[2],
+                self.viewport[3],
+            );
+            gl::Scissor(
+                self.scissor_box[0],
+                self.scissor_box[1],
+                self.scissor_box[2],
+                self.scissor_box[3],
+            );
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, self.unpack_alignment);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, self.unpack_row_length);
+            gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, self.unpack_skip_pixels);
+            gl::PixelStorei(gl::UNPACK_SKIP_ROWS, self.unpack_skip_rows);
+
+            if let Some(desktop) = &self.desktop {
+                (if desktop.framebuffer_srgb {
+                    gl::Enable
+                } else {
+                    gl::Disable
+                })(gl::FRAMEBUFFER_SRGB);
+                gl::PolygonMode(gl::FRONT_AND_BACK, desktop.polygon_mode[0] as gl::GLenum);
+            }
+            gl::ColorMask(
+                self.color_mask[0],
+                self.color_mask[1],
+                self.color_mask[2],
+                self.color_mask[3],
+            );
+        }
+    }
+}
@@ -0,0 +1,1158 @@
+use ash::prelude::VkResult;
+use ash::vk;
+use ash::vk::Handle;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const VS_SPV: &[u8] = include_bytes!("shaders/egui.vert.spv");
+const FS_SPV: &[u8] = include_bytes!("shaders/egui.frag.spv");
+
+const MAX_TEXTURES: u32 = 1024;
+
+/// Set once a paint callback was skipped, to only say so once.
+static CALLBACK_SKIPPED: AtomicBool = AtomicBool::new(false);
+
+struct Buffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+}
+
+struct Texture {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    descriptor_set: vk::DescriptorSet,
+}
+
+struct Frame {
+    image_view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    semaphore: vk::Semaphore,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+    /// Whether `fence` is yet to be waited for.
+    pending: bool,
+
+    /// Resources that may still be in use by the last submission of this frame.
+    stale_buffers: Vec<Buffer>,
+    stale_textures: Vec<Texture>,
+}
+
+struct Target {
+    format: vk::Format,
+    extent: vk::Extent2D,
+    frames: Vec<Frame>,
+}
+
+/// `PFN_vkSetDeviceLoaderData` of the loader's `vk_layer.h`.
+pub type SetDeviceLoaderData =
+    unsafe extern "system" fn(device: vk::Device, object: *mut c_void) -> vk::Result;
+
+pub struct Painter {
+    device: ash::Device,
+    set_device_loader_data: SetDeviceLoaderData,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    command_pool: vk::CommandPool,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    sampler: vk::Sampler,
+    vert_shader: vk::ShaderModule,
+    frag_shader: vk::ShaderModule,
+    pipelines: HashMap<vk::Format, (vk::RenderPass, vk::Pipeline)>,
+    targets: HashMap<vk::SwapchainKHR, Target>,
+    textures: HashMap<egui::TextureId, Texture>,
+}
+
+pub fn create_shader_module(device: &ash::Device, spv: &[u8]) -> VkResult<vk::ShaderModule> {
+    let code = ash::util::read_spv(&mut std::io::Cursor::new(spv))
+        .map_err(|_| vk::Result::ERROR_INITIALIZATION_FAILED)?;
+    unsafe { device.create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(&code), None) }
+}
+
+fn is_srgb(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB | vk::Format::A8B8G8R8_SRGB_PACK32
+    )
+}
+
+fn image_to_rgba(image: &egui::ImageData) -> ([usize; 2], Vec<u8>) {
+    let pixels: Vec<egui::Color32> = match image {
+        egui::epaint::image::ImageData::Color(c) => c.pixels.clone(),
+        egui::epaint::image::ImageData::Font(c) => c.srgba_pixels(1.0).collect(),
+    };
+    let size = image.size();
+    assert_eq!(size[0] * size[1], pixels.len());
+
+    let mut upixels: Vec<u8> = Vec::with_capacity(pixels.len() * 4);
+    for srgba in pixels {
+        upixels.push(srgba.r());
+        upixels.push(srgba.g());
+        upixels.push(srgba.b());
+        upixels.push(srgba.a());
+    }
+    (size, upixels)
+}
+
+impl Painter {
+    /// Creates what the swapchains of `device` share.
+    pub fn new(
+        device: ash::Device,
+        set_device_loader_data: SetDeviceLoaderData,
+        memory_properties: vk::PhysicalDeviceMemoryProperties,
+        queue_family_index: u32,
+    ) -> VkResult<Painter> {
+        let mut painter = Painter {
+            device,
+            set_device_loader_data,
+            memory_properties,
+            command_pool: vk::CommandPool::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            sampler: vk::Sampler::null(),
+            vert_shader: vk::ShaderModule::null(),
+            frag_shader: vk::ShaderModule::null(),
+            pipelines: HashMap::new(),
+            targets: HashMap::new(),
+            textures: HashMap::new(),
+        };
+        // on error, dropping it destroys what was created, null handles are ignored
+        painter.init(queue_family_index)?;
+        Ok(painter)
+    }
+
+    fn init(&mut self, queue_family_index: u32) -> VkResult<()> {
+        unsafe {
+            self.command_pool = self.device.create_command_pool(
+                &vk::CommandPoolCreateInfo::builder()
+                    .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                    .queue_family_index(queue_family_index),
+                None,
+            )?;
+
+            let pool_sizes = [
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::SAMPLED_IMAGE,
+                    descriptor_count: MAX_TEXTURES,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::SAMPLER,
+                    descriptor_count: MAX_TEXTURES,
+                },
+            ];
+            self.descriptor_pool = self.device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+                    .max_sets(MAX_TEXTURES)
+                    .pool_sizes(&pool_sizes),
+                None,
+            )?;
+
+            let bindings = [
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(0)
+                    .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .build(),
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(1)
+                    .descriptor_type(vk::DescriptorType::SAMPLER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .build(),
+            ];
+            self.descriptor_set_layout = self.device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings),
+                None,
+            )?;
+
+            // screen size in points, then whether the target applies the sRGB encoding itself
+            let push_constant_ranges = [vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: 12,
+            }];
+            let set_layouts = [self.descriptor_set_layout];
+            self.pipeline_layout = self.device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&set_layouts)
+                    .push_constant_ranges(&push_constant_ranges),
+                None,
+            )?;
+
+            self.sampler = self.device.create_sampler(
+                &vk::SamplerCreateInfo::builder()
+                    .mag_filter(vk::Filter::LINEAR)
+                    .min_filter(vk::Filter::LINEAR)
+                    .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+                None,
+            )?;
+
+            self.vert_shader = create_shader_module(&self.device, VS_SPV)?;
+            self.frag_shader = create_shader_module(&self.device, FS_SPV)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes `object` with `init`, or destroys it with `destroy` if that fails.
+    /// Objects start out with null handles, which destroying ignores.
+    fn build<T>(
+        &self,
+        mut object: T,
+        init: impl FnOnce(&Self, &mut T) -> VkResult<()>,
+        destroy: impl FnOnce(&Self, T),
+    ) -> VkResult<T> {
+        match init(self, &mut object) {
+            Ok(()) => Ok(object),
+            Err(e) => {
+                destroy(self, object);
+                Err(e)
+            }
+        }
+    }
+
+    fn find_memory_type(&self, type_bits: u32, flags: vk::MemoryPropertyFlags) -> VkResult<u32> {
+        (0..self.memory_properties.memory_type_count)
+            .find(|i| {
+                type_bits & (1 << i) != 0
+                    && self.memory_properties.memory_types[*i as usize]
+                        .property_flags
+                        .contains(flags)
+            })
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+    }
+
+    fn create_pipeline(&mut self, format: vk::Format) -> VkResult<(vk::RenderPass, vk::Pipeline)> {
+        if let Some(p) = self.pipelines.get(&format) {
+            return Ok(*p);
+        }
+
+        unsafe {
+            // the overlay is drawn on top of what the application already presents
+            let attachments = [vk::AttachmentDescription::builder()
+                .format(format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::LOAD)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .build()];
+            let color_refs = [vk::AttachmentReference {
+                attachment: 0,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            }];
+            let subpasses = [vk::SubpassDescription::builder()
+                .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                .color_attachments(&color_refs)
+                .build()];
+            let dependencies = [vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::empty(),
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            }];
+            let render_pass = self.device.create_render_pass(
+                &vk::RenderPassCreateInfo::builder()
+                    .attachments(&attachments)
+                    .subpasses(&subpasses)
+                    .dependencies(&dependencies),
+                None,
+            )?;
+
+            let entry_point = std::ffi::CString::new("main").unwrap();
+            let stages = [
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(self.vert_shader)
+                    .name(&entry_point)
+                    .build(),
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(self.frag_shader)
+                    .name(&entry_point)
+                    .build(),
+            ];
+
+            let vertex_bindings = [vk::VertexInputBindingDescription {
+                binding: 0,
+                stride: std::mem::size_of::<egui::epaint::Vertex>() as u32,
+                input_rate: vk::VertexInputRate::VERTEX,
+            }];
+            let vertex_attributes = [
+                // a_pos
+                vk::VertexInputAttributeDescription {
+                    location: 0,
+                    binding: 0,
+                    format: vk::Format::R32G32_SFLOAT,
+                    offset: 0,
+                },
+                // a_tc
+                vk::VertexInputAttributeDescription {
+                    location: 1,
+                    binding: 0,
+                    format: vk::Format::R32G32_SFLOAT,
+                    offset: 8,
+                },
+                // a_srgba
+                vk::VertexInputAttributeDescription {
+                    location: 2,
+                    binding: 0,
+                    format: vk::Format::R8G8B8A8_UNORM,
+                    offset: 16,
+                },
+            ];
+            let vertex_input = vk::PipelineVertexInputStateCreateInfo::builder()
+                .vertex_binding_descriptions(&vertex_bindings)
+                .vertex_attribute_descriptions(&vertex_attributes);
+            let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+                .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+            let viewport = vk::PipelineViewportStateCreateInfo::builder()
+                .viewport_count(1)
+                .scissor_count(1);
+            let rasterization = vk::PipelineRasterizationStateCreateInfo::builder()
+                .polygon_mode(vk::PolygonMode::FILL)
+                .cull_mode(vk::CullModeFlags::NONE)
+                .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+                .line_width(1.);
+            let multisample = vk::PipelineMultisampleStateCreateInfo::builder()
+                .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+            let blend_attachments = [vk::PipelineColorBlendAttachmentState {
+                blend_enable: vk::TRUE,
+                // premultiplied alpha
+                src_color_blend_factor: vk::BlendFactor::ONE,
+                dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE,
+                alpha_blend_op: vk::BlendOp::ADD,
+                color_write_mask: vk::ColorComponentFlags::RGBA,
+            }];
+            let color_blend =
+                vk::PipelineColorBlendStateCreateInfo::builder().attachments(&blend_attachments);
+            let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+            let dynamic =
+                vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+            let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder();
+
+            let pipelines = self.device.create_graphics_pipelines(
+                vk::PipelineCache::null(),
+                &[vk::GraphicsPipelineCreateInfo::builder()
+                    .stages(&stages)
+                    .vertex_input_state(&vertex_input)
+                    .input_assembly_state(&input_assembly)
+                    .viewport_state(&viewport)
+                    .rasterization_state(&rasterization)
+                    .multisample_state(&multisample)
+                    .depth_stencil_state(&depth_stencil)
+                    .color_blend_state(&color_blend)
+                    .dynamic_state(&dynamic)
+                    .layout(self.pipeline_layout)
+                    .render_pass(render_pass)
+                    .subpass(0)
+                    .build()],
+                None,
+            );
+            let pipeline = match pipelines {
+                Ok(pipelines) => pipelines[0],
+                Err((_, e)) => {
+                    self.device.destroy_render_pass(render_pass, None);
+                    return Err(e);
+                }
+            };
+
+            self.pipelines.insert(format, (render_pass, pipeline));
+            Ok((render_pass, pipeline))
+        }
+    }
+
+    pub fn add_swapchain(
+        &mut self,
+        swapchain: vk::SwapchainKHR,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        images: &[vk::Image],
+    ) -> VkResult<()> {
+        let (render_pass, _) = self.create_pipeline(format)?;
+
+        let command_buffers = unsafe {
+            self.device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::builder()
+                    .command_pool(self.command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(images.len() as u32),
+            )?
+        };
+
+        let mut frames = Vec::with_capacity(images.len());
+        for (i, (image, command_buffer)) in images.iter().zip(&command_buffers).enumerate() {
+            match self.create_frame(render_pass, format, extent, *image, *command_buffer) {
+                Ok(frame) => frames.push(frame),
+                Err(e) => {
+                    let unused = &command_buffers[i + 1..];
+                    if !unused.is_empty() {
+                        unsafe { self.device.free_command_buffers(self.command_pool, unused) };
+                    }
+                    for frame in frames {
+                        self.destroy_frame(frame);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        self.targets.insert(
+            swapchain,
+            Target {
+                format,
+                extent,
+                frames,
+            },
+        );
+        Ok(())
+    }
+
+    /// Creates what drawing on `image` takes, `command_buffer` is freed on error.
+    fn create_frame(
+        &self,
+        render_pass: vk::RenderPass,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        image: vk::Image,
+        command_buffer: vk::CommandBuffer,
+    ) -> VkResult<Frame> {
+        let frame = Frame {
+            image_view: vk::ImageView::null(),
+            framebuffer: vk::Framebuffer::null(),
+            command_buffer,
+            fence: vk::Fence::null(),
+            semaphore: vk::Semaphore::null(),
+            vertex_buffer: None,
+            index_buffer: None,
+            pending: false,
+            stale_buffers: vec![],
+            stale_textures: vec![],
+        };
+        self.build(
+            frame,
+            |painter, frame| unsafe {
+                // command buffers are dispatchable, and since they are allocated below the
+                // loader they must be given the dispatch table of their device through it
+                (painter.set_device_loader_data)(
+                    painter.device.handle(),
+                    command_buffer.as_raw() as *mut c_void,
+                )
+                .result()?;
+
+                frame.image_view = painter.device.create_image_view(
+                    &vk::ImageViewCreateInfo::builder()
+                        .image(image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(format)
+                        .subresource_range(vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level: 0,
+                            level_count: 1,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }),
+                    None,
+                )?;
+                let attachments = [frame.image_view];
+                frame.framebuffer = painter.device.create_framebuffer(
+                    &vk::FramebufferCreateInfo::builder()
+                        .render_pass(render_pass)
+                        .attachments(&attachments)
+                        .width(extent.width)
+                        .height(extent.height)
+                        .layers(1),
+                    None,
+                )?;
+                frame.fence = painter
+                    .device
+                    .create_fence(&vk::FenceCreateInfo::builder(), None)?;
+                frame.semaphore = painter
+                    .device
+                    .create_semaphore(&vk::SemaphoreCreateInfo::builder(), None)?;
+                Ok(())
+            },
+            Self::destroy_frame,
+        )
+    }
+
+    pub fn remove_swapchain(&mut self, swapchain: vk::SwapchainKHR) {
+        if let Some(target) = self.targets.remove(&swapchain) {
+            for frame in target.frames {
+                self.destroy_frame(frame);
+            }
+        }
+    }
+
+    fn destroy_frame(&self, mut frame: Frame) {
+        unsafe {
+            if frame.pending {
+                // fails once the device is lost, which also ends its work
+                let _ = self.device.wait_for_fences(&[frame.fence], true, u64::MAX);
+            }
+            self.release_stale(&mut frame);
+            if let Some(b) = frame.vertex_buffer {
+                self.destroy_buffer(b);
+            }
+            if let Some(b) = frame.index_buffer {
+                self.destroy_buffer(b);
+            }
+            self.device
+                .free_command_buffers(self.command_pool, &[frame.command_buffer]);
+            self.device.destroy_framebuffer(frame.framebuffer, None);
+            self.device.destroy_image_view(frame.image_view, None);
+            self.device.destroy_fence(frame.fence, None);
+            self.device.destroy_semaphore(frame.semaphore, None);
+        }
+    }
+
+    fn release_stale(&self, frame: &mut Frame) {
+        for b in frame.stale_buffers.drain(..) {
+            self.destroy_buffer(b);
+        }
+        for t in frame.stale_textures.drain(..) {
+            self.destroy_texture(t);
+        }
+    }
+
+    fn create_buffer(&self, size: vk::DeviceSize, usage: vk::BufferUsageFlags) -> VkResult<Buffer> {
+        let buffer = Buffer {
+            buffer: vk::Buffer::null(),
+            memory: vk::DeviceMemory::null(),
+            size,
+        };
+        self.build(
+            buffer,
+            |painter, buffer| unsafe {
+                buffer.buffer = painter.device.create_buffer(
+                    &vk::BufferCreateInfo::builder()
+                        .size(size)
+                        .usage(usage)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                    None,
+                )?;
+                let requirements = painter.device.get_buffer_memory_requirements(buffer.buffer);
+                buffer.memory = painter.device.allocate_memory(
+                    &vk::MemoryAllocateInfo::builder()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(painter.find_memory_type(
+                            requirements.memory_type_bits,
+                            vk::MemoryPropertyFlags::HOST_VISIBLE
+                                | vk::MemoryPropertyFlags::HOST_COHERENT,
+                        )?),
+                    None,
+                )?;
+                painter
+                    .device
+                    .bind_buffer_memory(buffer.buffer, buffer.memory, 0)
+            },
+            Self::destroy_buffer,
+        )
+    }
+
+    fn write_buffer(&self, buffer: &Buffer, data: &[u8]) -> VkResult<()> {
+        unsafe {
+            let ptr = self.device.map_memory(
+                buffer.memory,
+                0,
+                buffer.size,
+                vk::MemoryMapFlags::empty(),
+            )?;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr as *mut u8, data.len());
+            self.device.unmap_memory(buffer.memory);
+        }
+        Ok(())
+    }
+
+    fn destroy_buffer(&self, buffer: Buffer) {
+        unsafe {
+            self.device.destroy_buffer(buffer.buffer, None);
+            self.device.free_memory(buffer.memory, None);
+        }
+    }
+
+    fn create_texture(&self, size: [usize; 2]) -> VkResult<Texture> {
+        let texture = Texture {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            descriptor_set: vk::DescriptorSet::null(),
+        };
+        self.build(
+            texture,
+            |painter, texture| unsafe {
+                texture.image = painter.device.create_image(
+                    &vk::ImageCreateInfo::builder()
+                        .image_type(vk::ImageType::TYPE_2D)
+                        .format(vk::Format::R8G8B8A8_SRGB)
+                        .extent(vk::Extent3D {
+                            width: size[0] as u32,
+                            height: size[1] as u32,
+                            depth: 1,
+                        })
+                        .mip_levels(1)
+                        .array_layers(1)
+                        .samples(vk::SampleCountFlags::TYPE_1)
+                        .tiling(vk::ImageTiling::OPTIMAL)
+                        .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .initial_layout(vk::ImageLayout::UNDEFINED),
+                    None,
+                )?;
+                let requirements = painter.device.get_image_memory_requirements(texture.image);
+                texture.memory = painter.device.allocate_memory(
+                    &vk::MemoryAllocateInfo::builder()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(painter.find_memory_type(
+                            requirements.memory_type_bits,
+                            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                        )?),
+                    None,
+                )?;
+                painter
+                    .device
+                    .bind_image_memory(texture.image, texture.memory, 0)?;
+
+                texture.view = painter.device.create_image_view(
+                    &vk::ImageViewCreateInfo::builder()
+                        .image(texture.image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(vk::Format::R8G8B8A8_SRGB)
+                        .subresource_range(vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level: 0,
+                            level_count: 1,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }),
+                    None,
+                )?;
+
+                let set_layouts = [painter.descriptor_set_layout];
+                texture.descriptor_set = painter.device.allocate_descriptor_sets(
+                    &vk::DescriptorSetAllocateInfo::builder()
+                        .descriptor_pool(painter.descriptor_pool)
+                        .set_layouts(&set_layouts),
+                )?[0];
+                let image_infos = [vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: texture.view,
+                    image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                }];
+                let sampler_infos = [vk::DescriptorImageInfo {
+                    sampler: painter.sampler,
+                    image_view: vk::ImageView::null(),
+                    image_layout: vk::ImageLayout::UNDEFINED,
+                }];
+                painter.device.update_descriptor_sets(
+                    &[
+                        vk::WriteDescriptorSet::builder()
+                            .dst_set(texture.descriptor_set)
+                            .dst_binding(0)
+                            .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                            .image_info(&image_infos)
+                            .build(),
+                        vk::WriteDescriptorSet::builder()
+                            .dst_set(texture.descriptor_set)
+                            .dst_binding(1)
+                            .descriptor_type(vk::DescriptorType::SAMPLER)
+                            .image_info(&sampler_infos)
+                            .build(),
+                    ],
+                    &[],
+                );
+                Ok(())
+            },
+            Self::destroy_texture,
+        )
+    }
+
+    fn destroy_texture(&self, texture: Texture) {
+        unsafe {
+            if texture.descriptor_set != vk::DescriptorSet::null() {
+                // only fails when out of host memory, the pool releases the set anyway
+                let _ = self
+                    .device
+                    .free_descriptor_sets(self.descriptor_pool, &[texture.descriptor_set]);
+            }
+            self.device.destroy_image_view(texture.view, None);
+            self.device.destroy_image(texture.image, None);
+            self.device.free_memory(texture.memory, None);
+        }
+    }
+
+    /// Records the upload of `delta` into `command_buffer`, the staging buffers are
+    /// handed back so that they can be released once the submission is done.
+    fn set_texture_delta(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        s: egui::epaint::ahash::AHashMap<
+            egui::TextureId,
+            egui::epaint::ImageDelta,
+            egui::epaint::ahash::RandomState,
+        >,
+        stale_buffers: &mut Vec<Buffer>,
+        stale_textures: &mut Vec<Texture>,
+    ) -> VkResult<()> {
+        for (id, imdelta) in s {
+            let (size, pixels) = image_to_rgba(&imdelta.image);
+
+            let staging = self.create_buffer(
+                pixels.len() as vk::DeviceSize,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+            )?;
+            let written = self.write_buffer(&staging, &pixels);
+            let staging_buffer = staging.buffer;
+            stale_buffers.push(staging);
+            written?;
+
+            let (image, old_layout, offset) = match imdelta.pos {
+                Some([x, y]) => {
+                    let texture = match self.textures.get(&id) {
+                        Some(texture) => texture,
+                        None => {
+                            eprintln!("overlib: partial update of unknown texture {:?}", id);
+                            continue;
+                        }
+                    };
+                    (
+                        texture.image,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::Offset3D {
+                            x: x as i32,
+                            y: y as i32,
+                            z: 0,
+                        },
+                    )
+                }
+                None => {
+                    let texture = self.create_texture(size)?;
+                    let image = texture.image;
+                    if let Some(old) = self.textures.insert(id, texture) {
+                        stale_textures.push(old);
+                    }
+                    (
+                        image,
+                        vk::ImageLayout::UNDEFINED,
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                    )
+                }
+            };
+
+            let range = vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            };
+            unsafe {
+                self.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[vk::ImageMemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::SHADER_READ)
+                        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .old_layout(old_layout)
+                        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(image)
+                        .subresource_range(range)
+                        .build()],
+                );
+                self.device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[vk::BufferImageCopy {
+                        buffer_offset: 0,
+                        buffer_row_length: 0,
+                        buffer_image_height: 0,
+                        image_subresource: vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        },
+                        image_offset: offset,
+                        image_extent: vk::Extent3D {
+                            width: size[0] as u32,
+                            height: size[1] as u32,
+                            depth: 1,
+                        },
+                    }],
+                );
+                self.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[vk::ImageMemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(image)
+                        .subresource_range(range)
+                        .build()],
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure `buffer` can hold `size` bytes, growing it if needed.
+    fn ensure_buffer(
+        &self,
+        buffer: Option<Buffer>,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+    ) -> VkResult<Buffer> {
+        match buffer {
+            Some(b) if b.size >= size => Ok(b),
+            old => {
+                if let Some(b) = old {
+                    self.destroy_buffer(b);
+                }
+                self.create_buffer(size.next_power_of_two().max(4096), usage)
+            }
+        }
+    }
+
+    /// Records and submits the overlay for `image_index` of `swapchain`.
+    ///
+    /// The submission waits on `wait_semaphores`, and the returned semaphore is
+    /// signaled once the overlay is drawn: presentation should wait on it instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint_jobs(
+        &mut self,
+        queue: vk::Queue,
+        swapchain: vk::SwapchainKHR,
+        image_index: u32,
+        wait_semaphores: &[vk::Semaphore],
+        meshes: Vec<egui::ClippedPrimitive>,
+        pixels_per_point: f32,
+        delta: egui::TexturesDelta,
+    ) -> VkResult<vk::Semaphore> {
+        let target = match self.targets.get_mut(&swapchain) {
+            Some(target) if (image_index as usize) < target.frames.len() => target,
+            // add_swapchain failed for it
+            _ => return Err(vk::Result::ERROR_UNKNOWN),
+        };
+        let (format, extent) = (target.format, target.extent);
+        let mut frame = target.frames.swap_remove(image_index as usize);
+
+        let result = self.record(
+            &mut frame,
+            queue,
+            format,
+            extent,
+            wait_semaphores,
+            meshes,
+            pixels_per_point,
+            delta.set,
+        );
+
+        // freed textures may still be sampled by this submission or the previous ones
+        for id in delta.free {
+            if let Some(texture) = self.textures.remove(&id) {
+                frame.stale_textures.push(texture);
+            }
+        }
+
+        let semaphore = frame.semaphore;
+        match self.targets.get_mut(&swapchain) {
+            Some(target) => {
+                target.frames.push(frame);
+                let last = target.frames.len() - 1;
+                target.frames.swap(image_index as usize, last);
+            }
+            None => self.destroy_frame(frame),
+        }
+        result.map(|()| semaphore)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record(
+        &mut self,
+        frame: &mut Frame,
+        queue: vk::Queue,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        wait_semaphores: &[vk::Semaphore],
+        meshes: Vec<egui::ClippedPrimitive>,
+        pixels_per_point: f32,
+        set: egui::epaint::ahash::AHashMap<
+            egui::TextureId,
+            egui::epaint::ImageDelta,
+            egui::epaint::ahash::RandomState,
+        >,
+    ) -> VkResult<()> {
+        let (render_pass, pipeline) = self.create_pipeline(format)?;
+
+        unsafe {
+            if frame.pending {
+                self.device
+                    .wait_for_fences(&[frame.fence], true, u64::MAX)?;
+                frame.pending = false;
+            }
+            self.release_stale(frame);
+
+            let command_buffer = frame.command_buffer;
+            self.device
+                .reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())?;
+            self.device.begin_command_buffer(
+                command_buffer,
+                &vk::CommandBufferBeginInfo::builder()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+
+            self.set_texture_delta(
+                command_buffer,
+                set,
+                &mut frame.stale_buffers,
+                &mut frame.stale_textures,
+            )?;
+
+            let mut vertices: Vec<egui::epaint::Vertex> = vec![];
+            let mut indices: Vec<u32> = vec![];
+            let mut draws = vec![];
+            for egui::ClippedPrimitive {
+                clip_rect,
+                primitive,
+            } in meshes
+            {
+                if let egui::epaint::Primitive::Mesh(mesh) = primitive {
+                    draws.push((
+                        clip_rect,
+                        mesh.texture_id,
+                        indices.len() as u32,
+                        vertices.len() as i32,
+                        mesh.indices.len() as u32,
+                    ));
+                    vertices.extend_from_slice(&mesh.vertices);
+                    indices.extend_from_slice(&mesh.indices);
+                } else if !CALLBACK_SKIPPED.swap(true, Ordering::Relaxed) {
+                    // panicking would unwind into vkQueuePresentKHR and abort the application
+                    eprintln!(
+                        "overlib: paint callbacks aren't supported with Vulkan, skipping them"
+                    );
+                }
+            }
+
+            let vertex_bytes = std::slice::from_raw_parts(
+                vertices.as_ptr() as *const u8,
+                vertices.len() * std::mem::size_of::<egui::epaint::Vertex>(),
+            );
+            let index_bytes = std::slice::from_raw_parts(
+                indices.as_ptr() as *const u8,
+                indices.len() * std::mem::size_of::<u32>(),
+            );
+            let vertex_buffer = self.ensure_buffer(
+                frame.vertex_buffer.take(),
+                vertex_bytes.len() as vk::DeviceSize,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+            )?;
+            let vertex_buffer = &*frame.vertex_buffer.insert(vertex_buffer);
+            let index_buffer = self.ensure_buffer(
+                frame.index_buffer.take(),
+                index_bytes.len() as vk::DeviceSize,
+                vk::BufferUsageFlags::INDEX_BUFFER,
+            )?;
+            let index_buffer = &*frame.index_buffer.insert(index_buffer);
+            self.write_buffer(vertex_buffer, vertex_bytes)?;
+            self.write_buffer(index_buffer, index_bytes)?;
+
+            self.device.cmd_begin_render_pass(
+                command_buffer,
+                &vk::RenderPassBeginInfo::builder()
+                    .render_pass(render_pass)
+                    .framebuffer(frame.framebuffer)
+                    .render_area(vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent,
+                    }),
+                vk::SubpassContents::INLINE,
+            );
+            self.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline,
+            );
+            self.device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+
+            let screen_size_pixels = egui::vec2(extent.width as f32, extent.height as f32);
+            let screen_size_points = screen_size_pixels / pixels_per_point;
+            let mut push_constants = [0u8; 12];
+            push_constants[0..4].copy_from_slice(&screen_size_points.x.to_ne_bytes());
+            push_constants[4..8].copy_from_slice(&screen_size_points.y.to_ne_bytes());
+            push_constants[8..12].copy_from_slice(&(is_srgb(format) as u32).to_ne_bytes());
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &push_constants,
+            );
+
+            self.device
+                .cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.buffer], &[0]);
+            self.device.cmd_bind_index_buffer(
+                command_buffer,
+                index_buffer.buffer,
+                0,
+                vk::IndexType::UINT32,
+            );
+
+            for (clip_rect, texture_id, first_index, vertex_offset, index_count) in draws {
+                let clip_min_x = pixels_per_point * clip_rect.min.x;
+                let clip_min_y = pixels_per_point * clip_rect.min.y;
+                let clip_max_x = pixels_per_point * clip_rect.max.x;
+                let clip_max_y = pixels_per_point * clip_rect.max.y;
+                let clip_min_x = clip_min_x.clamp(0.0, screen_size_pixels.x);
+                let clip_min_y = clip_min_y.clamp(0.0, screen_size_pixels.y);
+                let clip_max_x = clip_max_x.clamp(clip_min_x, screen_size_pixels.x);
+                let clip_max_y = clip_max_y.clamp(clip_min_y, screen_size_pixels.y);
+                let clip_min_x = clip_min_x.round() as i32;
+                let clip_min_y = clip_min_y.round() as i32;
+                let clip_max_x = clip_max_x.round() as i32;
+                let clip_max_y = clip_max_y.round() as i32;
+                if clip_max_x <= clip_min_x || clip_max_y <= clip_min_y {
+                    continue;
+                }
+
+                let descriptor_set = match self.textures.get(&texture_id) {
+                    Some(t) => t.descriptor_set,
+                    None => continue,
+                };
+
+                // unlike GL, the scissor Y coordinate is from the top
+                self.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    &[vk::Rect2D {
+                        offset: vk::Offset2D {
+                            x: clip_min_x,
+                            y: clip_min_y,
+                        },
+                        extent: vk::Extent2D {
+                            width: (clip_max_x - clip_min_x) as u32,
+                            height: (clip_max_y - clip_min_y) as u32,
+                        },
+                    }],
+                );
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[descriptor_set],
+                    &[],
+                );
+                self.device.cmd_draw_indexed(
+                    command_buffer,
+                    index_count,
+                    1,
+                    first_index,
+                    vertex_offset,
+                    0,
+                );
+            }
+
+            self.device.cmd_end_render_pass(command_buffer);
+            self.device.end_command_buffer(command_buffer)?;
+
+            let wait_stages =
+                vec![vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; wait_semaphores.len()];
+            let command_buffers = [command_buffer];
+            let signal_semaphores = [frame.semaphore];
+            self.device.reset_fences(&[frame.fence])?;
+            self.device.queue_submit(
+                queue,
+                &[vk::SubmitInfo::builder()
+                    .wait_semaphores(wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .command_buffers(&command_buffers)
+                    .signal_semaphores(&signal_semaphores)
+                    .build()],
+                frame.fence,
+            )?;
+            frame.pending = true;
+        }
+        Ok(())
+    }
+
+    pub fn cleanup(&mut self) {
+        unsafe {
+            // fails once the device is lost, which also ends its work
+            let _ = self.device.device_wait_idle();
+            let swapchains: Vec<vk::SwapchainKHR> = self.targets.keys().copied().collect();
+            for swapchain in swapchains {
+                self.remove_swapchain(swapchain);
+            }
+            for (_, texture) in std::mem::take(&mut self.textures) {
+                self.destroy_texture(texture);
+            }
+            for (_, (render_pass, pipeline)) in std::mem::take(&mut self.pipelines) {
+                self.device.destroy_pipeline(pipeline, None);
+                self.device.destroy_render_pass(render_pass, None);
+            }
+            self.device.destroy_shader_module(self.vert_shader, None);
+            self.device.destroy_shader_module(self.frag_shader, None);
+            self.device.destroy_sampler(self.sampler, None);
+            self.device
+                .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
+    }
+}
+
+impl Drop for Painter {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
@@ -0,0 +1,31 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec2 screen_size;
+    uint linear_target;
+} pc;
+
+layout(set = 0, binding = 0) uniform texture2D u_texture;
+layout(set = 0, binding = 1) uniform sampler u_sampler;
+
+layout(location = 0) in vec4 v_rgba;
+layout(location = 1) in vec2 v_tc;
+
+layout(location = 0) out vec4 f_color;
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 rgb) {
+    vec3 lower = rgb * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, step(rgb, vec3(0.0031308)));
+}
+
+void main() {
+    // The texture is set up as `R8G8B8A8_SRGB`, so sampling already returns linear values
+    vec4 color = v_rgba * texture(sampler2D(u_texture, u_sampler), v_tc);
+    // swapchains with an UNORM format don't encode the output for us
+    if (pc.linear_target == 0u) {
+        color = vec4(srgb_from_linear(color.rgb), color.a);
+    }
+    f_color = color;
+}
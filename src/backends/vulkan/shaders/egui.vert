@@ -0,0 +1,28 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec2 screen_size;
+    uint linear_target;
+} pc;
+
+layout(location = 0) in vec2 a_pos;
+layout(location = 1) in vec2 a_tc;
+layout(location = 2) in vec4 a_srgba; // 0-1 sRGB
+
+layout(location = 0) out vec4 v_rgba;
+layout(location = 1) out vec2 v_tc;
+
+// 0-1 linear  from  0-1 sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, step(srgb, vec3(0.04045)));
+}
+
+void main() {
+    // Vulkan's clip space has y pointing down, just like egui
+    gl_Position = vec4(2.0 * a_pos.x / pc.screen_size.x - 1.0, 2.0 * a_pos.y / pc.screen_size.y - 1.0, 0.0, 1.0);
+    // egui encodes vertex colors in gamma spaces, so we must decode the colors here:
+    v_rgba = vec4(linear_from_srgb(a_srgba.rgb), a_srgba.a);
+    v_tc = a_tc;
+}
@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::CONFIG;
+use crate::frame_clock::{percentile, FrameStats};
+use crate::hotkeys::{self, Action};
+use crate::hud::HUD;
+use crate::logging::{self, RendererInfo};
+use crate::widgets::{FrameInfo, OverlayWidget};
+
+lazy_static! {
+    pub static ref BENCHMARK: Mutex<Benchmark> = Mutex::new(Benchmark::from_env());
+}
+
+/// A frame taking this many times the median frame time counts as a stutter.
+const STUTTER_FACTOR: u32 = 2;
+
+/// How long the path of the report stays on screen.
+const RESULT_DISPLAY: Duration = Duration::from_secs(5);
+
+/// Owned copy of `RendererInfo` taken when the run starts.
+struct Environment {
+    frontend: String,
+    gpu: String,
+    driver: String,
+    width: u32,
+    height: u32,
+}
+
+enum State {
+    Disabled,
+    /// Starts with the next frame.
+    Pending,
+    Running {
+        start: Instant,
+        frame_times: Vec<Duration>,
+        environment: Environment,
+    },
+    Done {
+        at: Instant,
+        report: Option<PathBuf>,
+    },
+}
+
+/// Records frame times from the first frame on, then writes a JSON report.
+pub struct Benchmark {
+    /// Runs without a duration last until the `stop_benchmark` hotkey is pressed.
+    duration: Option<Duration>,
+    state: State,
+}
+
+impl Benchmark {
+    /// Reads `OVERLIB_BENCHMARK` (`1` to enable) and `OVERLIB_BENCHMARK_DURATION` in seconds.
+    pub fn from_env() -> Self {
+        let enabled = matches!(
+            std::env::var("OVERLIB_BENCHMARK").as_deref(),
+            Ok("1") | Ok("true")
+        );
+        Benchmark {
+            duration: logging::env_secs("OVERLIB_BENCHMARK_DURATION").filter(|d| !d.is_zero()),
+            state: if enabled {
+                State::Pending
+            } else {
+                State::Disabled
+            },
+        }
+    }
+
+    /// Records a frame that took `frame_time`.
+    pub fn frame(&mut self, frame_time: Duration, info: &RendererInfo) {
+        match &mut self.state {
+            State::Pending => {
+                // the frame ending now started before the run did
+                self.state = State::Running {
+                    start: Instant::now(),
+                    frame_times: vec![],
+                    environment: Environment {
+                        frontend: info.frontend.to_string(),
+                        gpu: info.gpu.to_string(),
+                        driver: info.driver.to_string(),
+                        width: info.width,
+                        height: info.height,
+                    },
+                };
+            }
+            State::Running {
+                start, frame_times, ..
+            } => {
+                frame_times.push(frame_time);
+                let elapsed = start.elapsed();
+                if matches!(self.duration, Some(duration) if elapsed >= duration) {
+                    self.finish();
+                }
+            }
+            State::Disabled | State::Done { .. } => {}
+        }
+    }
+
+    /// Ends a running benchmark early.
+    pub fn stop(&mut self) {
+        if let State::Running { .. } = self.state {
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        let state = std::mem::replace(
+            &mut self.state,
+            State::Done {
+                at: Instant::now(),
+                report: None,
+            },
+        );
+        if let State::Running {
+            start,
+            frame_times,
+            environment,
+        } = state
+        {
+            let path = logging::output_folder().join(format!(
+                "{}_{}_benchmark.json",
+                logging::executable_name(),
+                logging::local_timestamp()
+            ));
+            let report = report(start.elapsed(), frame_times, &environment);
+            match std::fs::write(&path, serde_json::to_string_pretty(&report).unwrap()) {
+                Ok(()) => {
+                    eprintln!("benchmark report written to {}", path.display());
+                    self.state = State::Done {
+                        at: Instant::now(),
+                        report: Some(path),
+                    };
+                }
+                Err(e) => eprintln!("could not write {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Draws the progress of the run, then where its report went, in `corner`.
+    pub fn show(&self, ctx: &egui::Context, (align, offset): (egui::Align2, egui::Vec2)) {
+        let window = egui::Window::new("benchmark")
+            .resizable(false)
+            .anchor(align, offset);
+        match &self.state {
+            State::Running { start, .. } => {
+                let elapsed = start.elapsed();
+                window.show(ctx, |ui| match self.duration {
+                    Some(duration) => {
+                        let left = duration.saturating_sub(elapsed);
+                        ui.add(
+                            egui::ProgressBar::new(elapsed.as_secs_f32() / duration.as_secs_f32())
+                                .text(format!("{} s left", left.as_secs() + 1)),
+                        );
+                    }
+                    None => {
+                        ui.label(format!("recording for {} s", elapsed.as_secs()));
+                        if let Some(binding) = hotkeys::binding(Action::StopBenchmark) {
+                            ui.label(format!("press {} to stop", binding));
+                        }
+                    }
+                });
+            }
+            State::Done { at, report } if at.elapsed() < RESULT_DISPLAY => {
+                window.show(ctx, |ui| match report {
+                    Some(path) => ui.label(format!("report written to {}", path.display())),
+                    None => ui.label("could not write the report"),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The progress of the run and where its report went, in the corner beside the HUD.
+pub struct Widget;
+
+impl OverlayWidget for Widget {
+    fn name(&self) -> &str {
+        "benchmark"
+    }
+
+    fn ui(&mut self, ctx: &egui::Context, _frame: &FrameInfo) {
+        let corner = HUD.lock().unwrap().corner_beside(&CONFIG.lock().unwrap());
+        BENCHMARK.lock().unwrap().show(ctx, corner);
+    }
+
+    fn order(&self) -> i32 {
+        10
+    }
+}
+
+fn report(
+    elapsed: Duration,
+    frame_times: Vec<Duration>,
+    environment: &Environment,
+) -> serde_json::Value {
+    let mut sorted = frame_times;
+    sorted.sort_unstable();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.;
+
+    let mut report = serde_json::json!({
+        "application": logging::executable_name(),
+        "frontend": environment.frontend,
+        "gpu": environment.gpu,
+        "driver": environment.driver,
+        "resolution": [environment.width, environment.height],
+        "duration": elapsed.as_secs_f64(),
+        "frames": sorted.len(),
+    });
+    let stats = match FrameStats::new(&sorted) {
+        Some(stats) => stats,
+        None => return report,
+    };
+
+    let median = percentile(&sorted, 50.);
+    let stutter_threshold = median * STUTTER_FACTOR;
+    let stutters = sorted.iter().filter(|t| **t > stutter_threshold).count();
+
+    report["fps"] = serde_json::json!({
+        "min": 1000. / ms(sorted[sorted.len() - 1]),
+        "max": 1000. / ms(sorted[0]).max(f64::EPSILON),
+        "avg": sorted.len() as f64 / elapsed.as_secs_f64(),
+        "1% low": stats.low_1,
+        "0.1% low": stats.low_0_1,
+    });
+    report["frame_time_ms"] = serde_json::json!({
+        "min": ms(sorted[0]),
+        "max": ms(sorted[sorted.len() - 1]),
+        "avg": ms(stats.avg_frame_time),
+        "p50": ms(median),
+        "p90": ms(percentile(&sorted, 90.)),
+        "p95": ms(percentile(&sorted, 95.)),
+        "p99": ms(percentile(&sorted, 99.)),
+        "p99.9": ms(percentile(&sorted, 99.9)),
+    });
+    report["stutters"] = serde_json::json!(stutters);
+    report["stutter_threshold_ms"] = serde_json::json!(ms(stutter_threshold));
+    report
+}
@@ -0,0 +1,770 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32};
+
+use crate::hotkeys::{Action, Binding};
+use crate::limiter::LimitMethod;
+use crate::logging;
+
+lazy_static! {
+    /// Modules built from the configuration lock it when first used, so it must
+    /// not be held while calling into them.
+    pub static ref CONFIG: Mutex<Config> = Mutex::new(Config::load());
+    /// Read by the watcher thread or changed with `set`, applied at the next swap.
+    static ref PENDING: Mutex<Pending> = Mutex::new(Pending::default());
+}
+
+static WATCH: Once = Once::new();
+
+/// How long the overlay tells about a reload or the errors of the configuration.
+const NOTICE_DISPLAY: Duration = Duration::from_secs(8);
+
+/// How long to wait before watching again when no directory could be watched.
+const WATCH_RETRY: Duration = Duration::from_secs(5);
+
+/// Settings read from the configuration file, see `Config::set` for the keys.
+#[derive(Clone)]
+pub struct Config {
+    /// Widgets of the HUD: the FPS, the frame time statistics and the frame time graph.
+    pub fps: bool,
+    pub frametime: bool,
+    pub frame_timing: bool,
+    pub position: Align2,
+    /// Pixels per egui point.
+    pub scale: f32,
+    pub background_color: Color32,
+    pub text_color: Color32,
+    pub frametime_color: Color32,
+    /// Size of body text in points, egui's own sizes are kept without it.
+    pub font_size: Option<f32>,
+    /// Contents of `font_file`.
+    pub font: Option<Vec<u8>>,
+    pub hotkeys: Vec<(Action, String)>,
+    pub fps_limit: Option<f64>,
+    pub fps_limit_method: Option<LimitMethod>,
+    /// Names of the widgets to draw, in order, see `crate::widgets`.
+    pub widgets: Option<Vec<String>>,
+    /// Whether to listen on the control socket, see `crate::control`.
+    pub control: bool,
+    /// Whether to publish frame statistics in shared memory, see `crate::shm_stats`.
+    pub shm_stats: bool,
+    /// Whether the style and fonts changed since they were last given to egui.
+    style_changed: bool,
+    /// Lines that could not be applied, for the overlay to show.
+    errors: Vec<String>,
+    read_at: Instant,
+    reloaded: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            fps: true,
+            frametime: true,
+            frame_timing: true,
+            position: Align2::LEFT_TOP,
+            scale: 1.,
+            background_color: Color32::from_rgba_unmultiplied(2, 2, 2, 128),
+            text_color: Color32::WHITE,
+            frametime_color: Color32::from_rgb(0, 255, 0),
+            font_size: None,
+            font: None,
+            hotkeys: vec![],
+            fps_limit: None,
+            fps_limit_method: None,
+            widgets: None,
+            control: false,
+            shm_stats: false,
+            style_changed: true,
+            errors: vec![],
+            read_at: Instant::now(),
+            reloaded: false,
+        }
+    }
+}
+
+/// Settings `Config::set` knows, besides the hotkey actions.
+const KEYS: [&str; 16] = [
+    "fps",
+    "frametime",
+    "frame_timing",
+    "position",
+    "scale",
+    "background_color",
+    "background_alpha",
+    "text_color",
+    "frametime_color",
+    "font_size",
+    "font_file",
+    "fps_limit",
+    "fps_limit_method",
+    "widgets",
+    "control",
+    "shm_stats",
+];
+
+/// Environment variable with settings in the `MANGOHUD_CONFIG` syntax, applied
+/// over the configuration file.
+const CONFIG_STR: &str = "OVERLIB_CONFIG_STR";
+
+/// Our name for a MangoHud setting that has another one in overlib.
+fn alias(key: &str) -> &str {
+    match key {
+        "toggle_hud" => "toggle_overlay",
+        "toggle_hud_position" => "cycle_position",
+        "reload_cfg" => "reload_config",
+        key => key,
+    }
+}
+
+fn supports(key: &str) -> bool {
+    let key = alias(key);
+    KEYS.contains(&key) || Action::from_name(key).is_some()
+}
+
+/// `OVERLIB_CONFIG`, or `overlib/overlib.conf` in the XDG config directory.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("OVERLIB_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("overlib").join("overlib.conf"))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(format!("expected 1 or 0, got {:?}", value)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got {:?}", value))
+}
+
+/// `RRGGBB` in hexadecimal, with or without a leading `#`.
+fn parse_color(value: &str) -> Result<Color32, String> {
+    let hex = value.trim_start_matches('#');
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) if hex.len() == 6 => Ok(Color32::from_rgb(
+            (rgb >> 16) as u8,
+            (rgb >> 8) as u8,
+            rgb as u8,
+        )),
+        _ => Err(format!("expected a RRGGBB color, got {:?}", value)),
+    }
+}
+
+fn parse_position(value: &str) -> Result<Align2, String> {
+    Ok(match value {
+        "top-left" => Align2::LEFT_TOP,
+        "top-right" => Align2::RIGHT_TOP,
+        "bottom-left" => Align2::LEFT_BOTTOM,
+        "bottom-right" => Align2::RIGHT_BOTTOM,
+        _ => return Err(format!("unknown position {:?}", value)),
+    })
+}
+
+impl Config {
+    /// Reads the configuration file then `OVERLIB_CONFIG_STR`, defaults are used
+    /// for whatever neither sets.
+    pub fn load() -> Self {
+        let mut config = Config::default();
+        if let Some(path) = path() {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => config.parse(&text, &path.display().to_string()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => config.error(format!("could not read {}: {}", path.display(), e)),
+            }
+        }
+        if let Ok(string) = std::env::var(CONFIG_STR) {
+            config.parse_env(&string);
+        }
+        config
+    }
+
+    /// Marks a configuration read again, for the overlay to tell.
+    fn reloaded(mut self) -> Self {
+        self.reloaded = true;
+        eprintln!("configuration reloaded");
+        self
+    }
+
+    fn error(&mut self, error: String) {
+        eprintln!("{}", error);
+        self.errors.push(error);
+    }
+
+    /// Applies the `key=value` lines outside of sections, then the ones of the
+    /// `[executable]` section of the running program so its profile wins.
+    fn parse(&mut self, text: &str, origin: &str) {
+        let executable = logging::executable_name();
+        for profile in [None, Some(executable.as_str())] {
+            let mut section = None;
+            for (number, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    section = Some(name.trim());
+                    continue;
+                }
+                if section != profile {
+                    continue;
+                }
+                // a key alone turns the setting on, like in MangoHud
+                let (key, value) = line.split_once('=').unwrap_or((line, "1"));
+                if let Err(e) = self.set(key.trim(), value.trim()) {
+                    self.error(format!("{}:{}: {}", origin, number + 1, e));
+                }
+            }
+        }
+    }
+
+    /// Applies comma separated settings written like `MANGOHUD_CONFIG`, e.g.
+    /// `fps,frametime,position=top-right,font_size=20`. Keys overlib doesn't know
+    /// are skipped, so the same string can be given to both.
+    fn parse_env(&mut self, string: &str) {
+        for item in string.split(',') {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            let (key, value) = item.split_once('=').unwrap_or((item, "1"));
+            let key = key.trim();
+            if !supports(key) {
+                eprintln!("{}: ignoring unsupported setting {:?}", CONFIG_STR, key);
+                continue;
+            }
+            if let Err(e) = self.set(key, value.trim()) {
+                self.error(format!("{}: {}", CONFIG_STR, e));
+            }
+        }
+    }
+
+    /// Sets one setting from its name and textual value.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let key = alias(key);
+        if let Some(action) = Action::from_name(key) {
+            if !value.is_empty() {
+                value.parse::<Binding>()?;
+            }
+            self.hotkeys.retain(|(a, _)| *a != action);
+            self.hotkeys.push((action, value.to_string()));
+            return Ok(());
+        }
+
+        match key {
+            "fps" => self.fps = parse_bool(value)?,
+            "frametime" => self.frametime = parse_bool(value)?,
+            "frame_timing" => self.frame_timing = parse_bool(value)?,
+            "position" => self.position = parse_position(value)?,
+            "scale" => match parse_number(value)? {
+                scale if scale > 0. => self.scale = scale,
+                _ => return Err("the scale has to be positive".to_string()),
+            },
+            "background_color" => {
+                let alpha = self.background_color.a();
+                let [r, g, b, _] = parse_color(value)?.to_array();
+                self.background_color = Color32::from_rgba_unmultiplied(r, g, b, alpha);
+            }
+            "background_alpha" => {
+                let alpha: f32 = parse_number(value)?;
+                let [r, g, b, _] = self.background_color.to_srgba_unmultiplied();
+                self.background_color = Color32::from_rgba_unmultiplied(
+                    r,
+                    g,
+                    b,
+                    (alpha.clamp(0., 1.) * 255.).round() as u8,
+                );
+            }
+            "text_color" => self.text_color = parse_color(value)?,
+            "frametime_color" => self.frametime_color = parse_color(value)?,
+            "font_size" => self.font_size = Some(parse_number(value)?),
+            "font_file" => {
+                let font =
+                    std::fs::read(value).map_err(|e| format!("could not read {}: {}", value, e))?;
+                self.font = Some(font);
+            }
+            // MangoHud cycles through limits joined by `+`, only the first one is used
+            "fps_limit" => {
+                let fps = parse_number(value.split('+').next().unwrap_or(""))?;
+                self.fps_limit = Some(crate::limiter::check_fps(fps)?)
+            }
+            "fps_limit_method" => {
+                self.fps_limit_method = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("expected early or late, got {:?}", value))?,
+                )
+            }
+            "widgets" => {
+                self.widgets = Some(
+                    value
+                        .split('+')
+                        .map(|w| w.trim().to_string())
+                        .filter(|w| !w.is_empty())
+                        .collect(),
+                )
+            }
+            "control" => self.control = parse_bool(value)?,
+            "shm_stats" => self.shm_stats = parse_bool(value)?,
+            _ => return Err(format!("unknown setting {:?}", key)),
+        }
+        if key.starts_with("font_") || key.ends_with("_color") || key == "background_alpha" {
+            self.style_changed = true;
+        }
+        Ok(())
+    }
+
+    fn style(&self) -> egui::Style {
+        let mut style = egui::Style::default();
+        style.visuals.widgets.noninteractive.bg_fill = self.background_color;
+        style.visuals.override_text_color = Some(self.text_color);
+        if let Some(size) = self.font_size {
+            use egui::{FontId, TextStyle};
+            style.text_styles = [
+                (TextStyle::Small, FontId::proportional(size * 0.7)),
+                (TextStyle::Body, FontId::proportional(size)),
+                (TextStyle::Button, FontId::proportional(size)),
+                (TextStyle::Heading, FontId::proportional(size * 1.4)),
+                (TextStyle::Monospace, FontId::monospace(size)),
+            ]
+            .into();
+        }
+        style
+    }
+
+    fn fonts(&self) -> egui::FontDefinitions {
+        let mut fonts = egui::FontDefinitions::default();
+        if let Some(font) = &self.font {
+            fonts.font_data.insert(
+                "overlib".to_string(),
+                egui::FontData::from_owned(font.clone()),
+            );
+            for family in fonts.families.values_mut() {
+                family.insert(0, "overlib".to_string());
+            }
+        }
+        fonts
+    }
+}
+
+/// Gives egui the configured style and fonts when they changed.
+pub fn apply_style(ctx: &egui::Context) {
+    let mut config = CONFIG.lock().unwrap();
+    if !config.style_changed {
+        return;
+    }
+    config.style_changed = false;
+    ctx.set_style(config.style());
+    ctx.set_fonts(config.fonts());
+}
+
+/// Replaces the active configuration, everything built from it follows.
+fn apply(config: Config) {
+    crate::limiter::LIMITER.lock().unwrap().configure(&config);
+    crate::hotkeys::configure(&config);
+    crate::hud::HUD.lock().unwrap().configure();
+    crate::widgets::configure(&config);
+    *CONFIG.lock().unwrap() = config;
+}
+
+#[derive(Default)]
+struct Pending {
+    config: Option<Config>,
+    /// What `set` changed, in order, applied again over each reload of the file.
+    overrides: Vec<(String, String)>,
+}
+
+impl Pending {
+    /// Replaces what is pending with `config`, read again, and the overrides on top.
+    fn reload(&mut self, mut config: Config) {
+        for (key, value) in &self.overrides {
+            // a font file may have gone away since
+            if let Err(e) = config.set(key, value) {
+                config.error(format!("{}: {}", key, e));
+            }
+        }
+        self.config = Some(config);
+    }
+}
+
+/// Reads the configuration again, for the next swap to apply.
+fn load_pending() {
+    // read before locking, `set` doesn't wait for the file
+    let config = Config::load().reloaded();
+    PENDING.lock().unwrap().reload(config);
+}
+
+fn apply_pending() {
+    let pending = PENDING.lock().unwrap().config.take();
+    if let Some(config) = pending {
+        apply(config);
+    }
+}
+
+/// Reads the configuration file again and applies it right away.
+pub fn reload() {
+    load_pending();
+    apply_pending();
+}
+
+/// Changes one setting of the active configuration at the next swap. It stays
+/// changed when the file is read again.
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    let mut pending = PENDING.lock().unwrap();
+    let mut config = match pending.config.take() {
+        Some(config) => config,
+        None => CONFIG.lock().unwrap().clone(),
+    };
+    let set = config.set(key, value);
+    pending.config = Some(config);
+    if set.is_ok() {
+        let key = alias(key);
+        pending.overrides.retain(|(k, _)| alias(k) != key);
+        pending.overrides.push((key.to_string(), value.to_string()));
+    }
+    set
+}
+
+/// Starts watching the configuration file, then applies what the watcher read
+/// since the last call. Called once per frame.
+pub fn poll() {
+    WATCH.call_once(|| {
+        if let Some(path) = path() {
+            let spawn = std::thread::Builder::new()
+                .name("overlib-config".to_string())
+                .spawn(move || watch(&path));
+            if let Err(e) = spawn {
+                eprintln!("could not watch the configuration: {}", e);
+            }
+        }
+    });
+    apply_pending();
+}
+
+/// Reads the configuration again whenever it is written. The directory is watched
+/// rather than the file, since editors often replace files instead of writing to them.
+///
+/// Until the directory exists, its nearest existing parent is watched for it to be
+/// created, and watching is retried every `WATCH_RETRY` when that fails too.
+fn watch(path: &Path) {
+    let (dir, name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => return,
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+
+    unsafe {
+        let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+        if fd < 0 {
+            eprintln!(
+                "could not watch the configuration: {}",
+                std::io::Error::last_os_error()
+            );
+            return;
+        }
+
+        let mut waited = false;
+        loop {
+            let watched = dir
+                .ancestors()
+                .find(|d| d.is_dir())
+                .unwrap_or_else(|| Path::new("."));
+            // the next directory on the way to the configuration's one
+            let next = dir
+                .strip_prefix(watched)
+                .ok()
+                .and_then(|rest| rest.iter().next());
+            let mask = match next {
+                None => libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_DELETE,
+                Some(_) => libc::IN_CREATE | libc::IN_MOVED_TO,
+            } | libc::IN_DELETE_SELF
+                | libc::IN_MOVE_SELF;
+            let c_dir = CString::new(watched.as_os_str().as_bytes()).unwrap();
+            let wd = libc::inotify_add_watch(fd, c_dir.as_ptr(), mask);
+            if wd < 0 {
+                if !waited {
+                    eprintln!(
+                        "could not watch {}: {}",
+                        watched.display(),
+                        std::io::Error::last_os_error()
+                    );
+                }
+                std::thread::sleep(WATCH_RETRY);
+                waited = true;
+                continue;
+            }
+            // written while its directory wasn't watched yet
+            if next.is_none() && waited && path.exists() {
+                load_pending();
+            }
+            waited = true;
+
+            if !read_watch(fd, wd, name.as_bytes(), next.map(|n| n.as_bytes())) {
+                libc::close(fd);
+                return;
+            }
+            libc::inotify_rm_watch(fd, wd);
+        }
+    }
+}
+
+/// Reads the events of watch `wd` on `fd`, reloading the configuration when `name`
+/// changes. Returns once the watch has to move, as `next` was created or the
+/// watched directory went away, or `false` if `fd` can't be read.
+unsafe fn read_watch(fd: libc::c_int, wd: libc::c_int, name: &[u8], next: Option<&[u8]>) -> bool {
+    // aligned for the event headers
+    let mut buffer = [0u32; 1024];
+    loop {
+        let len = libc::read(fd, buffer.as_mut_ptr() as *mut _, 4096);
+        if len < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return false;
+        }
+
+        let bytes = std::slice::from_raw_parts(buffer.as_ptr() as *const u8, len as usize);
+        let mut changed = false;
+        let mut moved = false;
+        let mut offset = 0;
+        while offset + std::mem::size_of::<libc::inotify_event>() <= bytes.len() {
+            let event = &*(bytes.as_ptr().add(offset) as *const libc::inotify_event);
+            let start = offset + std::mem::size_of::<libc::inotify_event>();
+            let event_name = &bytes[start..start + event.len as usize];
+            // names are padded with nuls
+            let event_name = event_name.split(|b| *b == 0).next().unwrap_or(&[]);
+            let gone = libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED;
+            // the previous watches may still have events queued
+            if event.wd == wd {
+                if event.mask & gone != 0 {
+                    moved = true;
+                } else if next.is_some() {
+                    moved |= Some(event_name) == next;
+                } else {
+                    changed |= event_name == name;
+                }
+            }
+            offset = start + event.len as usize;
+        }
+        if changed {
+            load_pending();
+        }
+        if moved {
+            return true;
+        }
+    }
+}
+
+/// Says the configuration was reloaded, or what was wrong with it.
+pub fn show_notice(ctx: &egui::Context) {
+    let config = CONFIG.lock().unwrap();
+    if config.read_at.elapsed() >= NOTICE_DISPLAY || (!config.reloaded && config.errors.is_empty())
+    {
+        return;
+    }
+    egui::Window::new("configuration")
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 8.))
+        .show(ctx, |ui| {
+            if config.errors.is_empty() {
+                ui.label("reloaded");
+            }
+            for error in &config.errors {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(key: &str, value: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        config.set(key, value).map(|()| config)
+    }
+
+    #[test]
+    fn booleans() {
+        assert!(set("fps", "1").unwrap().fps);
+        assert!(set("fps", "true").unwrap().fps);
+        assert!(!set("frametime", "0").unwrap().frametime);
+        assert!(!set("frame_timing", "false").unwrap().frame_timing);
+        assert!(set("control", "1").unwrap().control);
+        assert!(set("shm_stats", "1").unwrap().shm_stats);
+        assert!(set("fps", "yes").is_err());
+        assert!(set("fps", "").is_err());
+        assert!(set("fps", "2").is_err());
+    }
+
+    #[test]
+    fn positions() {
+        for (value, align) in [
+            ("top-left", Align2::LEFT_TOP),
+            ("top-right", Align2::RIGHT_TOP),
+            ("bottom-left", Align2::LEFT_BOTTOM),
+            ("bottom-right", Align2::RIGHT_BOTTOM),
+        ] {
+            assert_eq!(set("position", value).unwrap().position, align);
+        }
+        assert!(set("position", "center").is_err());
+        assert!(set("position", "Top-Left").is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(set("scale", "1.5").unwrap().scale, 1.5);
+        assert!(set("scale", "0").is_err());
+        assert!(set("scale", "-1").is_err());
+        assert!(set("scale", "big").is_err());
+        assert_eq!(set("font_size", "20").unwrap().font_size, Some(20.));
+        assert!(set("font_size", "").is_err());
+    }
+
+    #[test]
+    fn colors() {
+        let orange = Color32::from_rgb(0xff, 0x80, 0x00);
+        assert_eq!(set("text_color", "ff8000").unwrap().text_color, orange);
+        assert_eq!(set("text_color", "#ff8000").unwrap().text_color, orange);
+        assert_eq!(
+            set("frametime_color", "000000").unwrap().frametime_color,
+            Color32::BLACK
+        );
+        assert!(set("text_color", "fff").is_err());
+        assert!(set("text_color", "ff80000").is_err());
+        assert!(set("text_color", "gg0000").is_err());
+    }
+
+    #[test]
+    fn background_keeps_alpha_and_color() {
+        let mut config = Config::default();
+        config.set("background_alpha", "1").unwrap();
+        config.set("background_color", "ff0000").unwrap();
+        assert_eq!(config.background_color, Color32::from_rgb(0xff, 0, 0));
+
+        config.set("background_alpha", "0").unwrap();
+        assert_eq!(config.background_color.a(), 0);
+        // out of range alphas are clamped
+        config.set("background_alpha", "2").unwrap();
+        assert_eq!(config.background_color.a(), 255);
+        config.set("background_alpha", "-1").unwrap();
+        assert_eq!(config.background_color.a(), 0);
+        assert!(config.set("background_alpha", "half").is_err());
+    }
+
+    #[test]
+    fn fps_limits() {
+        assert_eq!(set("fps_limit", "60").unwrap().fps_limit, Some(60.));
+        assert_eq!(set("fps_limit", "60+30+0").unwrap().fps_limit, Some(60.));
+        assert_eq!(set("fps_limit", "0").unwrap().fps_limit, Some(0.));
+        assert_eq!(set("fps_limit", "1").unwrap().fps_limit, Some(1.));
+        for value in ["0.5", "-30", "inf", "NaN", "1e-320", "", "fast"] {
+            assert!(set("fps_limit", value).is_err(), "{:?}", value);
+        }
+
+        assert_eq!(
+            set("fps_limit_method", "early").unwrap().fps_limit_method,
+            Some(LimitMethod::Early)
+        );
+        assert_eq!(
+            set("fps_limit_method", "late").unwrap().fps_limit_method,
+            Some(LimitMethod::Late)
+        );
+        assert!(set("fps_limit_method", "soon").is_err());
+    }
+
+    #[test]
+    fn widgets() {
+        assert_eq!(
+            set("widgets", "hud+ metrics +").unwrap().widgets,
+            Some(vec!["hud".to_string(), "metrics".to_string()])
+        );
+        assert_eq!(set("widgets", "").unwrap().widgets, Some(vec![]));
+    }
+
+    #[test]
+    fn hotkeys() {
+        let mut config = Config::default();
+        config.set("toggle_hud", "Shift_L+F1").unwrap();
+        config.set("toggle_overlay", "F2").unwrap();
+        // the alias and our name are the same action, the last one wins
+        assert_eq!(
+            config.hotkeys,
+            vec![(Action::ToggleOverlay, "F2".to_string())]
+        );
+        // an empty binding unbinds
+        config.set("toggle_overlay", "").unwrap();
+        assert_eq!(config.hotkeys, vec![(Action::ToggleOverlay, String::new())]);
+        assert!(config.set("toggle_overlay", "NoSuchKey").is_err());
+        assert!(config.set("toggle_overlay", "F1+").is_err());
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(set("fps_color", "ffffff").is_err());
+        assert!(set("", "1").is_err());
+    }
+
+    #[test]
+    fn file() {
+        let text = format!(
+            "# comment\n\
+             \n\
+             fps=0\n\
+             frametime\n\
+             scale = 2\n\
+             position=middle\n\
+             [some_other_program]\n\
+             scale=4\n\
+             [{}]\n\
+             fps=1\n\
+             ",
+            logging::executable_name()
+        );
+        let mut config = Config::default();
+        config.parse(&text, "overlib.conf");
+        // the program's section wins over the lines outside of sections
+        assert!(config.fps);
+        assert!(config.frametime);
+        assert_eq!(config.scale, 2.);
+        assert_eq!(
+            config.errors,
+            vec!["overlib.conf:6: unknown position \"middle\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn env() {
+        let mut config = Config::default();
+        config.parse_env(" fps=0,,position=top-right, font_size=20,no_display,scale=x");
+        assert!(!config.fps);
+        assert_eq!(config.position, Align2::RIGHT_TOP);
+        assert_eq!(config.font_size, Some(20.));
+        // unsupported MangoHud keys are skipped, invalid values are errors
+        assert_eq!(
+            config.errors,
+            vec![format!("{}: expected a number, got \"x\"", CONFIG_STR)]
+        );
+
+        let mut config = Config::default();
+        config.parse_env("");
+        assert!(config.errors.is_empty());
+    }
+}
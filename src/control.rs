@@ -0,0 +1,251 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+
+use serde_json::{json, Value};
+
+use crate::config::{self, CONFIG};
+use crate::hotkeys::Action;
+use crate::logging::{self, RendererInfo};
+use crate::{frame_clock, hud, metrics};
+
+lazy_static! {
+    /// `overlib-<pid>.sock` in `XDG_RUNTIME_DIR`, or in `/tmp/overlib-<uid>` without it.
+    static ref PATH: PathBuf = {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(format!("/tmp/overlib-{}", unsafe { libc::geteuid() })));
+        dir.join(format!("overlib-{}.sock", std::process::id()))
+    };
+    /// Requests that need a frame to run, run by the next one.
+    static ref QUEUE: Mutex<Vec<Queued>> = Mutex::new(vec![]);
+}
+
+static LISTEN: Once = Once::new();
+
+/// Clients served at once, each has a thread.
+const MAX_CLIENTS: usize = 8;
+
+static CLIENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// The process that created the socket, its forks exit without removing it.
+static LISTENER_PID: AtomicU32 = AtomicU32::new(0);
+
+enum Queued {
+    Action(Action),
+    StartLogging,
+    StopLogging,
+}
+
+/// Starts listening once `control` is set, then runs what clients asked for since
+/// the last frame. Called once per frame.
+pub fn poll(info: &RendererInfo) {
+    if !LISTEN.is_completed() && CONFIG.lock().unwrap().control {
+        LISTEN.call_once(listen);
+    }
+
+    let queued = std::mem::take(&mut *QUEUE.lock().unwrap());
+    for request in queued {
+        match request {
+            Queued::Action(action) => crate::run_action(action, info),
+            Queued::StartLogging => {
+                let mut logger = logging::LOGGER.lock().unwrap();
+                if !logger.is_logging() {
+                    logger.start(info);
+                }
+            }
+            Queued::StopLogging => logging::LOGGER.lock().unwrap().stop(),
+        }
+    }
+}
+
+extern "C" fn remove_socket() {
+    if LISTENER_PID.load(Ordering::Relaxed) == std::process::id() {
+        let _ = std::fs::remove_file(&*PATH);
+    }
+}
+
+/// Creates `dir` if needed, and checks only the user can get into it.
+fn private_dir(dir: &Path) -> std::io::Result<()> {
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // not followed if it is a link, someone else could have made it
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir()
+        || metadata.uid() != unsafe { libc::geteuid() }
+        || metadata.mode() & 0o077 != 0
+    {
+        return Err(std::io::Error::other(
+            "not a directory only the user can access",
+        ));
+    }
+    Ok(())
+}
+
+fn listen() {
+    // the socket is reachable through its directory only, its own mode isn't
+    // respected everywhere
+    let dir = PATH.parent().unwrap();
+    if let Err(e) = private_dir(dir) {
+        eprintln!("could not listen in {}: {}", dir.display(), e);
+        return;
+    }
+    // a socket left by a process that had the same pid
+    let _ = std::fs::remove_file(&*PATH);
+    let listener = match UnixListener::bind(&*PATH) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("could not listen on {}: {}", PATH.display(), e);
+            return;
+        }
+    };
+    LISTENER_PID.store(std::process::id(), Ordering::Relaxed);
+    unsafe { libc::atexit(remove_socket) };
+
+    let spawn = std::thread::Builder::new()
+        .name("overlib-control".to_string())
+        .spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if CLIENTS.fetch_add(1, Ordering::Relaxed) >= MAX_CLIENTS {
+                    CLIENTS.fetch_sub(1, Ordering::Relaxed);
+                    let _ = writeln!(
+                        stream,
+                        "{}",
+                        json!({ "ok": false, "error": "too many clients" })
+                    );
+                    continue;
+                }
+                let spawn = std::thread::Builder::new()
+                    .name("overlib-client".to_string())
+                    .spawn(move || {
+                        serve(stream);
+                        CLIENTS.fetch_sub(1, Ordering::Relaxed);
+                    });
+                if spawn.is_err() {
+                    CLIENTS.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        });
+    match spawn {
+        Ok(_) => eprintln!("listening for commands on {}", PATH.display()),
+        Err(e) => eprintln!("could not listen for commands: {}", e),
+    }
+}
+
+/// Whether the client runs as the same user as the application.
+fn same_user(stream: &UnixStream) -> bool {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut _,
+            &mut len,
+        )
+    };
+    res == 0 && cred.uid == unsafe { libc::geteuid() }
+}
+
+/// Answers each line of JSON the client writes with one line of JSON.
+fn serve(stream: UnixStream) {
+    if !same_user(&stream) {
+        eprintln!("refusing a control client of another user");
+        return;
+    }
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str(&line) {
+            Ok(request) => match handle(&request) {
+                Ok(Value::Object(mut reply)) => {
+                    reply.insert("ok".to_string(), Value::Bool(true));
+                    Value::Object(reply)
+                }
+                Ok(_) => json!({ "ok": true }),
+                Err(error) => json!({ "ok": false, "error": error }),
+            },
+            Err(e) => json!({ "ok": false, "error": format!("invalid JSON: {}", e) }),
+        };
+        if writeln!(writer, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
+
+fn string<'a>(request: &'a Value, field: &str) -> Result<&'a str, String> {
+    request[field]
+        .as_str()
+        .ok_or_else(|| format!("{:?} must be a string", field))
+}
+
+fn queue(request: Queued) -> Result<Value, String> {
+    QUEUE.lock().unwrap().push(request);
+    Ok(Value::Null)
+}
+
+/// Runs a request like `{"cmd": "set", "key": "fps", "value": "0"}` and returns
+/// the fields of the reply besides `ok`.
+fn handle(request: &Value) -> Result<Value, String> {
+    let cmd = string(request, "cmd")?;
+    if let Some(action) = Action::from_name(cmd) {
+        return queue(Queued::Action(action));
+    }
+
+    match cmd {
+        "show" => hud::HUD.lock().unwrap().set_visible(true),
+        "hide" => hud::HUD.lock().unwrap().set_visible(false),
+        "start_logging" => return queue(Queued::StartLogging),
+        "stop_logging" => return queue(Queued::StopLogging),
+        "set" => {
+            // numbers and booleans are taken as they are written in the file
+            let value = match &request["value"] {
+                Value::String(value) => value.clone(),
+                Value::Bool(value) => (*value as u8).to_string(),
+                Value::Number(value) => value.to_string(),
+                _ => return Err("\"value\" must be a string".to_string()),
+            };
+            config::set(string(request, "key")?, &value)?;
+        }
+        "push_metric" => {
+            let value = request["value"]
+                .as_f64()
+                .ok_or_else(|| "\"value\" must be a number".to_string())?;
+            metrics::push_metric(string(request, "name")?, value);
+        }
+        "push_text" => {
+            let text = request["text"].as_str().map(str::to_string);
+            metrics::push_text(string(request, "key")?.to_string(), text);
+        }
+        "mark_event" => metrics::mark_event(string(request, "label")?.to_string()),
+        "fps" => {
+            let frames = frame_clock::RECENT.lock().unwrap();
+            let frame_time_ms = frames
+                .latest()
+                .map_or(0., |frame_time| frame_time.as_secs_f64() * 1000.);
+            return Ok(json!({ "fps": frames.fps(), "frame_time_ms": frame_time_ms }));
+        }
+        _ => return Err(format!("unknown command {:?}", cmd)),
+    }
+    Ok(Value::Null)
+}
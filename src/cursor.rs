@@ -0,0 +1,26 @@
+use egui::{Color32, Id, LayerId, Order, Shape, Stroke};
+
+/// Size of the arrow, in points.
+const SIZE: f32 = 16.;
+
+/// Draws an arrow where the pointer is, above every window. The application may
+/// hide its own cursor or not draw one at all while the overlay takes input.
+pub fn show(ctx: &egui::Context) {
+    let pos = match ctx.input().pointer.hover_pos() {
+        Some(pos) => pos,
+        None => return,
+    };
+
+    let points = vec![
+        pos,
+        pos + egui::vec2(0., SIZE),
+        pos + egui::vec2(SIZE * 0.3, SIZE * 0.75),
+        pos + egui::vec2(SIZE * 0.7, SIZE * 0.7),
+    ];
+    ctx.layer_painter(LayerId::new(Order::Tooltip, Id::new("overlib_cursor")))
+        .add(Shape::convex_polygon(
+            points,
+            Color32::WHITE,
+            Stroke::new(1., Color32::BLACK),
+        ));
+}
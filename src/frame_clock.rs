@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    /// egui's time is shared by every drawable, so it counts from one origin.
+    static ref START: Instant = Instant::now();
+    /// Frames of every drawable, for what reports the FPS outside of the overlay.
+    pub static ref RECENT: Mutex<RecentFrames> = Mutex::new(RecentFrames::default());
+}
+
+/// How far back `RecentFrames` keeps frames.
+const RECENT_WINDOW: Duration = Duration::from_secs(1);
+
+/// How many frame times are kept.
+pub const HISTORY_LEN: usize = 1024;
+
+/// How many of the latest frames `predicted_dt` averages.
+const PREDICTION_FRAMES: usize = 8;
+
+/// Timestamps of the presents of one drawable.
+#[derive(Default)]
+pub struct FrameClock {
+    last_present: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+}
+
+impl FrameClock {
+    /// Records a present happening now.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_present {
+            if self.frame_times.len() == HISTORY_LEN {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(now - last);
+        }
+        self.last_present = Some(now);
+    }
+
+    /// Seconds since the overlay was first drawn.
+    pub fn time(&self) -> f64 {
+        START.elapsed().as_secs_f64()
+    }
+
+    /// Expected duration of the next frame in seconds.
+    pub fn predicted_dt(&self) -> f32 {
+        let recent = self.frame_times.iter().rev().take(PREDICTION_FRAMES);
+        let count = recent.len();
+        if count == 0 {
+            return 1. / 60.;
+        }
+        recent.sum::<Duration>().as_secs_f32() / count as f32
+    }
+
+    /// Durations between the latest presents, oldest first.
+    pub fn frame_times(&self) -> &VecDeque<Duration> {
+        &self.frame_times
+    }
+}
+
+/// Frames presented during the last second and how long they took.
+#[derive(Default)]
+pub struct RecentFrames {
+    frames: VecDeque<(Instant, Duration)>,
+    total: Duration,
+}
+
+impl RecentFrames {
+    pub fn push(&mut self, frame_time: Duration) {
+        let now = Instant::now();
+        while let Some((at, old)) = self.frames.front().copied() {
+            if now - at <= RECENT_WINDOW {
+                break;
+            }
+            self.total -= old;
+            self.frames.pop_front();
+        }
+        self.frames.push_back((now, frame_time));
+        self.total += frame_time;
+    }
+
+    /// Average over the last second, 0 before any frame.
+    pub fn fps(&self) -> f64 {
+        fps(self.total / self.frames.len().max(1) as u32)
+    }
+
+    pub fn latest(&self) -> Option<Duration> {
+        self.frames.back().map(|(_, frame_time)| *frame_time)
+    }
+}
+
+/// Summary of a run of frame times.
+pub struct FrameStats {
+    pub avg_fps: f64,
+    pub avg_frame_time: Duration,
+    /// FPS at the 99th percentile frame time.
+    pub low_1: f64,
+    /// FPS at the 99.9th percentile frame time.
+    pub low_0_1: f64,
+}
+
+fn fps(frame_time: Duration) -> f64 {
+    if frame_time.is_zero() {
+        0.
+    } else {
+        1. / frame_time.as_secs_f64()
+    }
+}
+
+impl FrameStats {
+    pub fn new<'a>(frame_times: impl IntoIterator<Item = &'a Duration>) -> Option<Self> {
+        let mut sorted: Vec<Duration> = frame_times.into_iter().copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable();
+
+        let total: Duration = sorted.iter().sum();
+        let avg_frame_time = total / sorted.len() as u32;
+        Some(FrameStats {
+            avg_fps: fps(avg_frame_time),
+            avg_frame_time,
+            low_1: fps(percentile(&sorted, 99.)),
+            low_0_1: fps(percentile(&sorted, 99.9)),
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted frame times.
+pub fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    let rank = (percent / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -1,24 +1,34 @@
 use std::ffi::c_void;
-use std::ffi::CString;
-use std::sync::Mutex;
+use std::ffi::CStr;
 
-use glad_gl::gl;
+use super::Binding;
+use super::Frontend;
 
 lazy_static! {
     static ref EGL: Egl = Egl::new();
-    static ref CURRENT_FEATURES: Mutex<Vec<(gl::GLuint, bool)>> = Mutex::new(vec![]);
-    static ref PAINTER: Mutex<crate::backends::opengl::painter::Painter> =
-        Mutex::new(crate::backends::opengl::painter::Painter::new(800, 800));
 }
 
-static mut MUST_INIT: bool = true;
+const EGL_HEIGHT: i32 = 0x3056;
+const EGL_WIDTH: i32 = 0x3057;
+const EGL_DRAW: i32 = 0x3059;
+const EGL_READ: i32 = 0x305A;
 
-static PIXELS_PER_POINT: f32 = 1.;
+type SwapBuffersWithDamage =
+    unsafe extern "C" fn(*mut c_void, *mut c_void, *const i32, i32) -> libc::c_uint;
 
 struct Egl {
     _lib: dlopen::raw::Library,
     swap_buffers: unsafe extern "C" fn(*mut c_void, *mut c_void) -> libc::c_uint,
     get_proc_address: unsafe extern "C" fn(*const c_void) -> *mut c_void,
+    get_current_context: unsafe extern "C" fn() -> *mut c_void,
+    query_surface: unsafe extern "C" fn(*mut c_void, *mut c_void, i32, *mut i32) -> libc::c_uint,
+    destroy_context: unsafe extern "C" fn(*mut c_void, *mut c_void) -> libc::c_uint,
+    get_current_display: unsafe extern "C" fn() -> *mut c_void,
+    get_current_surface: unsafe extern "C" fn(i32) -> *mut c_void,
+    make_current:
+        unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) -> libc::c_uint,
+    swap_buffers_with_damage_khr: Option<SwapBuffersWithDamage>,
+    swap_buffers_with_damage_ext: Option<SwapBuffersWithDamage>,
 }
 
 impl Egl {
@@ -28,24 +38,97 @@ impl Egl {
             let swap_buffers = lib
                 .symbol_cstr(&std::ffi::CString::new("eglSwapBuffers").unwrap())
                 .unwrap();
-            let get_proc_address = lib
+            let get_proc_address: unsafe extern "C" fn(*const c_void) -> *mut c_void = lib
                 .symbol_cstr(&std::ffi::CString::new("eglGetProcAddress").unwrap())
                 .unwrap();
+            let get_current_context = lib
+                .symbol_cstr(&std::ffi::CString::new("eglGetCurrentContext").unwrap())
+                .unwrap();
+            let query_surface = lib
+                .symbol_cstr(&std::ffi::CString::new("eglQuerySurface").unwrap())
+                .unwrap();
+            let destroy_context = lib
+                .symbol_cstr(&std::ffi::CString::new("eglDestroyContext").unwrap())
+                .unwrap();
+            let get_current_display = lib
+                .symbol_cstr(&std::ffi::CString::new("eglGetCurrentDisplay").unwrap())
+                .unwrap();
+            let get_current_surface = lib
+                .symbol_cstr(&std::ffi::CString::new("eglGetCurrentSurface").unwrap())
+                .unwrap();
+            let make_current = lib
+                .symbol_cstr(&std::ffi::CString::new("eglMakeCurrent").unwrap())
+                .unwrap();
+            // extension entry points are only available through eglGetProcAddress
+            let swap_buffers_with_damage_khr =
+                std::mem::transmute::<*mut c_void, Option<SwapBuffersWithDamage>>(
+                    get_proc_address(c"eglSwapBuffersWithDamageKHR".as_ptr() as *const c_void),
+                );
+            let swap_buffers_with_damage_ext =
+                std::mem::transmute::<*mut c_void, Option<SwapBuffersWithDamage>>(
+                    get_proc_address(c"eglSwapBuffersWithDamageEXT".as_ptr() as *const c_void),
+                );
 
             Self {
                 _lib: lib,
                 swap_buffers,
                 get_proc_address,
+                get_current_context,
+                query_surface,
+                destroy_context,
+                get_current_display,
+                get_current_surface,
+                make_current,
+                swap_buffers_with_damage_khr,
+                swap_buffers_with_damage_ext,
             }
         }
     }
 }
 
-unsafe fn init(glx: &Egl) {
-    gl::load(|e| (glx.get_proc_address)(CString::new(e).unwrap().into_raw() as *const c_void));
-    MUST_INIT = false;
+impl Frontend for Egl {
+    type SwapResult = libc::c_uint;
+
+    const NAME: &'static str = "egl";
+
+    unsafe fn current_context(&self) -> *mut c_void {
+        (self.get_current_context)()
+    }
+
+    unsafe fn current_binding(&self) -> Binding {
+        Binding {
+            dpy: (self.get_current_display)(),
+            draw: (self.get_current_surface)(EGL_DRAW),
+            read: (self.get_current_surface)(EGL_READ),
+            context: (self.get_current_context)(),
+        }
+    }
+
+    unsafe fn make_current(&self, binding: &Binding) -> bool {
+        (self.make_current)(binding.dpy, binding.draw, binding.read, binding.context) != 0
+    }
+
+    unsafe fn drawable_size(&self, dpy: *mut c_void, drawable: *mut c_void) -> (u32, u32) {
+        let mut width = 0;
+        let mut height = 0;
+        (self.query_surface)(dpy, drawable, EGL_WIDTH, &mut width);
+        (self.query_surface)(dpy, drawable, EGL_HEIGHT, &mut height);
+        (width as u32, height as u32)
+    }
+
+    unsafe fn get_proc_address(&self, name: &CStr) -> *mut c_void {
+        (self.get_proc_address)(name.as_ptr() as *const c_void)
+    }
+
+    unsafe fn swap_buffers(&self, dpy: *mut c_void, drawable: *mut c_void) -> libc::c_uint {
+        (self.swap_buffers)(dpy, drawable)
+    }
 }
 
+/// What `dlsym` hands out for `eglSwapBuffers`.
+///
+/// # Safety
+/// Same as `eglSwapBuffers`.
 #[no_mangle]
 pub unsafe extern "C" fn overlib_egl_swap_buffers(
     dpy: *mut c_void,
@@ -54,98 +137,156 @@ pub unsafe extern "C" fn overlib_egl_swap_buffers(
     eglSwapBuffers(dpy, drawable)
 }
 
+/// What `dlsym` hands out for `eglGetProcAddress`.
+///
+/// # Safety
+/// Same as `eglGetProcAddress`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_egl_get_proc_address(
+    proc_name: *const libc::c_char,
+) -> *mut libc::c_void {
+    eglGetProcAddress(proc_name)
+}
+
+/// # Safety
+/// `proc_name` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn eglGetProcAddress(proc_name: *const libc::c_char) -> *mut libc::c_void {
+    match std::ffi::CStr::from_ptr(proc_name).to_str().unwrap() {
+        "eglSwapBuffers" => return eglSwapBuffers as _,
+        "eglDestroyContext" => return eglDestroyContext as _,
+        "eglSwapBuffersWithDamageKHR" if EGL.swap_buffers_with_damage_khr.is_some() => {
+            return eglSwapBuffersWithDamageKHR as _
+        }
+        "eglSwapBuffersWithDamageEXT" if EGL.swap_buffers_with_damage_ext.is_some() => {
+            return eglSwapBuffersWithDamageEXT as _
+        }
+        _ => {}
+    }
+
+    (EGL.get_proc_address)(proc_name as _)
+}
+
+/// # Safety
+/// `dpy` and `drawable` must be valid for the real `eglSwapBuffers`, with a
+/// context current on the calling thread.
 #[deny(non_snake_case)]
 #[no_mangle]
 pub unsafe extern "C" fn eglSwapBuffers(
     dpy: *mut c_void,
     drawable: *mut c_void,
 ) -> std::os::raw::c_uint {
-    let egl = &EGL;
-
-    if MUST_INIT {
-        init(egl);
-    }
+    super::render_overlay_frame(&*EGL, dpy, drawable)
+}
 
-    let mut max_texture_size: i32 = std::mem::zeroed();
-    gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size as *mut i32);
-
-    let mut viewport: [i32; 4] = std::mem::zeroed();
-    gl::GetIntegerv(gl::VIEWPORT, &mut viewport as *mut _);
-
-    let inputs = egui::RawInput {
-        screen_rect: Some(egui::Rect {
-            min: egui::Pos2 {
-                x: viewport[0] as f32,
-                y: viewport[1] as f32,
-            },
-            max: egui::Pos2 {
-                x: viewport[2] as f32,
-                y: viewport[3] as f32,
-            },
-        }),
-        pixels_per_point: Some(PIXELS_PER_POINT),
-        max_texture_side: Some(max_texture_size as usize),
-        time: None,
-        predicted_dt: 1. / 60.,
-        modifiers: egui::Modifiers::NONE,
-        events: vec![],
-        hovered_files: vec![],
-        dropped_files: vec![],
+/// Presents through `real` with the overlay's area added to the damage.
+unsafe fn swap_buffers_with_damage(
+    real: Option<SwapBuffersWithDamage>,
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    rects: *const i32,
+    n_rects: i32,
+) -> libc::c_uint {
+    let real = match real {
+        Some(real) => real,
+        None => return eglSwapBuffers(dpy, drawable),
     };
+    super::render_overlay_frame_with(&*EGL, dpy, drawable, |damage| match damage {
+        // no rectangles means the whole surface is damaged already
+        Some(rect) if n_rects > 0 => {
+            let mut all = std::slice::from_raw_parts(rects, n_rects as usize * 4).to_vec();
+            all.extend_from_slice(&rect);
+            real(dpy, drawable, all.as_ptr(), n_rects + 1)
+        }
+        _ => real(dpy, drawable, rects, n_rects),
+    })
+}
 
-    let full_output = crate::EGUI_CTX.run(inputs, crate::ui_fn);
-
-    set_required_features();
-
-    let mut program = 0;
-    gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program as *mut _ as *mut _);
-
-    PAINTER
-        .lock()
-        .unwrap()
-        .adjust_size(viewport[2], viewport[3]);
-    PAINTER.lock().unwrap().paint_jobs(
-        crate::EGUI_CTX.tessellate(full_output.shapes),
-        PIXELS_PER_POINT,
-        full_output.textures_delta,
-    );
-    gl::UseProgram(program);
+/// What `dlsym` hands out for `eglSwapBuffersWithDamageKHR`.
+///
+/// # Safety
+/// Same as `eglSwapBuffersWithDamageKHR`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_egl_swap_buffers_with_damage_khr(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    rects: *const i32,
+    n_rects: i32,
+) -> std::os::raw::c_uint {
+    eglSwapBuffersWithDamageKHR(dpy, drawable, rects, n_rects)
+}
 
-    let out = (egl.swap_buffers)(dpy, drawable);
+/// What `dlsym` hands out for `eglSwapBuffersWithDamageEXT`.
+///
+/// # Safety
+/// Same as `eglSwapBuffersWithDamageEXT`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_egl_swap_buffers_with_damage_ext(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    rects: *const i32,
+    n_rects: i32,
+) -> std::os::raw::c_uint {
+    eglSwapBuffersWithDamageEXT(dpy, drawable, rects, n_rects)
+}
 
-    restore_features();
+/// # Safety
+/// The arguments must be valid for the real `eglSwapBuffersWithDamageKHR`,
+/// `rects` holding `n_rects` rectangles of four integers.
+#[no_mangle]
+pub unsafe extern "C" fn eglSwapBuffersWithDamageKHR(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    rects: *const i32,
+    n_rects: i32,
+) -> std::os::raw::c_uint {
+    swap_buffers_with_damage(
+        EGL.swap_buffers_with_damage_khr,
+        dpy,
+        drawable,
+        rects,
+        n_rects,
+    )
+}
 
-    out
+/// # Safety
+/// The arguments must be valid for the real `eglSwapBuffersWithDamageEXT`,
+/// `rects` holding `n_rects` rectangles of four integers.
+#[no_mangle]
+pub unsafe extern "C" fn eglSwapBuffersWithDamageEXT(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    rects: *const i32,
+    n_rects: i32,
+) -> std::os::raw::c_uint {
+    swap_buffers_with_damage(
+        EGL.swap_buffers_with_damage_ext,
+        dpy,
+        drawable,
+        rects,
+        n_rects,
+    )
 }
 
-unsafe fn set_required_features() {
-    CURRENT_FEATURES.lock().unwrap().clear();
-    if gl::IsEnabled(gl::DEPTH_TEST) != 0 {
-        CURRENT_FEATURES
-            .lock()
-            .unwrap()
-            .push((gl::DEPTH_TEST, true));
-        gl::Disable(gl::DEPTH_TEST);
-    }
-    if gl::IsEnabled(gl::SCISSOR_TEST) == 0 {
-        CURRENT_FEATURES
-            .lock()
-            .unwrap()
-            .push((gl::SCISSOR_TEST, false));
-        gl::Enable(gl::SCISSOR_TEST);
-    }
-    if gl::IsEnabled(gl::BLEND) != 0 {
-        CURRENT_FEATURES.lock().unwrap().push((gl::BLEND, true));
-        gl::Disable(gl::BLEND);
-    }
-    if gl::IsEnabled(gl::CULL_FACE) != 0 {
-        CURRENT_FEATURES.lock().unwrap().push((gl::CULL_FACE, true));
-        gl::Disable(gl::CULL_FACE);
-    }
+/// What `dlsym` hands out for `eglDestroyContext`.
+///
+/// # Safety
+/// Same as `eglDestroyContext`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_egl_destroy_context(
+    dpy: *mut c_void,
+    ctx: *mut c_void,
+) -> std::os::raw::c_uint {
+    eglDestroyContext(dpy, ctx)
 }
 
-unsafe fn restore_features() {
-    for (feature, state) in CURRENT_FEATURES.lock().unwrap().to_vec() {
-        (if state { gl::Enable } else { gl::Disable })(feature);
-    }
+/// # Safety
+/// `dpy` and `ctx` must be valid for the real `eglDestroyContext`.
+#[no_mangle]
+pub unsafe extern "C" fn eglDestroyContext(
+    dpy: *mut c_void,
+    ctx: *mut c_void,
+) -> std::os::raw::c_uint {
+    super::destroy_context(&*EGL, dpy, ctx);
+    (EGL.destroy_context)(dpy, ctx)
 }
@@ -1,25 +1,46 @@
 use std::ffi::c_void;
-use std::ffi::CString;
-use std::sync::Mutex;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use glad_gl::gl;
+use super::Binding;
+use super::Frontend;
 
 lazy_static! {
     static ref GLX: Glx = Glx::new();
-    static ref CURRENT_FEATURES: Mutex<Vec<(gl::GLuint, bool)>> = Mutex::new(vec![]);
-    static ref PAINTER: Mutex<crate::backends::opengl::painter::Painter> =
-        Mutex::new(crate::backends::opengl::painter::Painter::new(800, 800));
 }
 
-static mut MUST_INIT: bool = true;
+const GLX_WIDTH: libc::c_int = 0x801D;
+const GLX_HEIGHT: libc::c_int = 0x801E;
 
-static PIXELS_PER_POINT: f32 = 1.;
+type SwapBuffersMscOml = unsafe extern "C" fn(*mut c_void, *mut c_void, i64, i64, i64) -> i64;
+
+type XErrorHandler = Option<unsafe extern "C" fn(*mut c_void, *mut c_void) -> libc::c_int>;
+
+/// Set by `record_error` when an X error arrived.
+static X_ERROR: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn record_error(_dpy: *mut c_void, _event: *mut c_void) -> libc::c_int {
+    X_ERROR.store(true, Ordering::Relaxed);
+    0
+}
 
 struct Glx {
     _lib: dlopen::raw::Library,
     swap_buffers: unsafe extern "C" fn(*mut c_void, *mut c_void),
     get_proc_address: unsafe extern "C" fn(*const c_void) -> *mut c_void,
+    get_current_context: unsafe extern "C" fn() -> *mut c_void,
     get_proc_address_arb: unsafe extern "C" fn(*const c_void) -> *mut c_void,
+    query_drawable: unsafe extern "C" fn(*mut c_void, *mut c_void, libc::c_int, *mut libc::c_uint),
+    destroy_context: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    get_current_display: unsafe extern "C" fn() -> *mut c_void,
+    get_current_drawable: unsafe extern "C" fn() -> *mut c_void,
+    get_current_read_drawable: unsafe extern "C" fn() -> *mut c_void,
+    make_context_current:
+        unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) -> libc::c_int,
+    swap_buffers_msc_oml: Option<SwapBuffersMscOml>,
+    _x11: dlopen::raw::Library,
+    set_error_handler: unsafe extern "C" fn(XErrorHandler) -> XErrorHandler,
+    sync: unsafe extern "C" fn(*mut c_void, libc::c_int) -> libc::c_int,
 }
 
 impl Glx {
@@ -29,33 +50,135 @@ impl Glx {
             let swap_buffers = lib
                 .symbol_cstr(&std::ffi::CString::new("glXSwapBuffers").unwrap())
                 .unwrap();
-            let get_proc_address = lib
+            let get_proc_address: unsafe extern "C" fn(*const c_void) -> *mut c_void = lib
                 .symbol_cstr(&std::ffi::CString::new("glXGetProcAddress").unwrap())
                 .unwrap();
+            let get_current_context = lib
+                .symbol_cstr(&std::ffi::CString::new("glXGetCurrentContext").unwrap())
+                .unwrap();
             let get_proc_address_arb = lib
                 .symbol_cstr(&std::ffi::CString::new("glXGetProcAddressARB").unwrap())
                 .unwrap();
+            let query_drawable = lib
+                .symbol_cstr(&std::ffi::CString::new("glXQueryDrawable").unwrap())
+                .unwrap();
+            let destroy_context = lib
+                .symbol_cstr(&std::ffi::CString::new("glXDestroyContext").unwrap())
+                .unwrap();
+            let get_current_display = lib
+                .symbol_cstr(&std::ffi::CString::new("glXGetCurrentDisplay").unwrap())
+                .unwrap();
+            let get_current_drawable = lib
+                .symbol_cstr(&std::ffi::CString::new("glXGetCurrentDrawable").unwrap())
+                .unwrap();
+            let get_current_read_drawable = lib
+                .symbol_cstr(&std::ffi::CString::new("glXGetCurrentReadDrawable").unwrap())
+                .unwrap();
+            let make_context_current = lib
+                .symbol_cstr(&std::ffi::CString::new("glXMakeContextCurrent").unwrap())
+                .unwrap();
+            let swap_buffers_msc_oml = std::mem::transmute::<*mut c_void, Option<SwapBuffersMscOml>>(
+                get_proc_address(c"glXSwapBuffersMscOML".as_ptr() as *const c_void),
+            );
+            let x11 = dlopen::raw::Library::open("libX11.so.6").unwrap();
+            let set_error_handler = x11
+                .symbol_cstr(&std::ffi::CString::new("XSetErrorHandler").unwrap())
+                .unwrap();
+            let sync = x11
+                .symbol_cstr(&std::ffi::CString::new("XSync").unwrap())
+                .unwrap();
 
             Self {
                 _lib: lib,
                 swap_buffers,
                 get_proc_address,
+                get_current_context,
                 get_proc_address_arb,
+                query_drawable,
+                destroy_context,
+                get_current_display,
+                get_current_drawable,
+                get_current_read_drawable,
+                make_context_current,
+                swap_buffers_msc_oml,
+                _x11: x11,
+                set_error_handler,
+                sync,
             }
         }
     }
 }
 
-unsafe fn init(glx: &Glx) {
-    gl::load(|e| (glx.get_proc_address)(CString::new(e).unwrap().into_raw() as *const c_void));
-    MUST_INIT = false;
+impl Frontend for Glx {
+    type SwapResult = ();
+
+    const NAME: &'static str = "glx";
+
+    unsafe fn current_context(&self) -> *mut c_void {
+        (self.get_current_context)()
+    }
+
+    unsafe fn current_binding(&self) -> Binding {
+        Binding {
+            dpy: (self.get_current_display)(),
+            draw: (self.get_current_drawable)(),
+            read: (self.get_current_read_drawable)(),
+            context: (self.get_current_context)(),
+        }
+    }
+
+    // the drawable may be gone and the context current on another thread, Xlib's
+    // default handler would exit the process on the errors that follow
+    unsafe fn make_current(&self, binding: &Binding) -> bool {
+        if binding.dpy.is_null() {
+            return false;
+        }
+        // errors of the application's earlier requests still go to its own handler
+        (self.sync)(binding.dpy, 0);
+        X_ERROR.store(false, Ordering::Relaxed);
+        let previous = (self.set_error_handler)(Some(record_error));
+        let made =
+            (self.make_context_current)(binding.dpy, binding.draw, binding.read, binding.context);
+        (self.sync)(binding.dpy, 0);
+        (self.set_error_handler)(previous);
+        made != 0 && !X_ERROR.load(Ordering::Relaxed)
+    }
+
+    // GLX drawables are X windows, or GLX windows sharing their XID
+    fn native_window(&self, drawable: *mut c_void) -> Option<u64> {
+        Some(drawable as u64)
+    }
+
+    unsafe fn drawable_size(&self, dpy: *mut c_void, drawable: *mut c_void) -> (u32, u32) {
+        let mut width = 0;
+        let mut height = 0;
+        (self.query_drawable)(dpy, drawable, GLX_WIDTH, &mut width);
+        (self.query_drawable)(dpy, drawable, GLX_HEIGHT, &mut height);
+        (width, height)
+    }
+
+    unsafe fn get_proc_address(&self, name: &CStr) -> *mut c_void {
+        (self.get_proc_address)(name.as_ptr() as *const c_void)
+    }
+
+    unsafe fn swap_buffers(&self, dpy: *mut c_void, drawable: *mut c_void) {
+        (self.swap_buffers)(dpy, drawable)
+    }
 }
 
+/// What `dlsym` hands out for `glXSwapBuffers`.
+///
+/// # Safety
+/// Same as `glXSwapBuffers`.
 #[no_mangle]
 pub unsafe extern "C" fn overlib_glx_swap_buffers(dpy: *mut c_void, drawable: *mut c_void) {
     glXSwapBuffers(dpy, drawable)
 }
 
+/// What `dlsym` hands out for `glXGetProcAddress`.
+///
+/// # Safety
+/// Same as `glXGetProcAddress`.
 #[no_mangle]
 pub unsafe extern "C" fn overlib_glx_get_proc_address(
     proc_name: *const libc::c_char,
@@ -64,6 +187,10 @@ pub unsafe extern "C" fn overlib_glx_get_proc_address(
     glXGetProcAddress(proc_name)
 }
 
+/// What `dlsym` hands out for `glXGetProcAddressARB`.
+///
+/// # Safety
+/// Same as `glXGetProcAddressARB`.
 #[no_mangle]
 pub unsafe extern "C" fn overlib_glx_get_proc_address_arb(
     proc_name: *const libc::c_char,
@@ -72,128 +199,98 @@ pub unsafe extern "C" fn overlib_glx_get_proc_address_arb(
     glXGetProcAddressARB(proc_name)
 }
 
+/// # Safety
+/// `proc_name` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn glXGetProcAddressARB(proc_name: *const libc::c_char) -> *mut libc::c_void {
     let glx = &GLX;
 
-    if MUST_INIT {
-        init(glx);
-    }
     match std::ffi::CStr::from_ptr(proc_name).to_str().unwrap() {
         "glXSwapBuffers" => return glXSwapBuffers as _,
+        "glXDestroyContext" => return glXDestroyContext as _,
+        "glXSwapBuffersMscOML" if GLX.swap_buffers_msc_oml.is_some() => {
+            return glXSwapBuffersMscOML as _
+        }
         _ => {}
     }
 
     (glx.get_proc_address_arb)(proc_name as _)
 }
+/// # Safety
+/// `proc_name` must be a valid nul-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn glXGetProcAddress(proc_name: *const libc::c_char) -> *mut libc::c_void {
     let glx = &GLX;
 
-    if MUST_INIT {
-        init(glx);
-    }
     match std::ffi::CStr::from_ptr(proc_name).to_str().unwrap() {
         "glXSwapBuffers" => return glXSwapBuffers as _,
+        "glXDestroyContext" => return glXDestroyContext as _,
+        "glXSwapBuffersMscOML" if GLX.swap_buffers_msc_oml.is_some() => {
+            return glXSwapBuffersMscOML as _
+        }
         _ => {}
     }
 
     (glx.get_proc_address)(proc_name as _)
 }
 
+/// # Safety
+/// `dpy` and `drawable` must be valid for the real `glXSwapBuffers`, with a
+/// context current on the calling thread.
 #[deny(non_snake_case)]
 #[no_mangle]
 pub unsafe extern "C" fn glXSwapBuffers(dpy: *mut c_void, drawable: *mut c_void) {
-    let glx = &GLX;
+    super::render_overlay_frame(&*GLX, dpy, drawable)
+}
 
-    if MUST_INIT {
-        init(glx);
-    }
+/// What `dlsym` hands out for `glXSwapBuffersMscOML`.
+///
+/// # Safety
+/// Same as `glXSwapBuffersMscOML`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_glx_swap_buffers_msc_oml(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    target_msc: i64,
+    divisor: i64,
+    remainder: i64,
+) -> i64 {
+    glXSwapBuffersMscOML(dpy, drawable, target_msc, divisor, remainder)
+}
 
-    let mut max_texture_size: i32 = std::mem::zeroed();
-    gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size as *mut i32);
-
-    let mut viewport: [i32; 4] = std::mem::zeroed();
-    gl::GetIntegerv(gl::VIEWPORT, &mut viewport as *mut _);
-
-    let inputs = egui::RawInput {
-        screen_rect: Some(egui::Rect {
-            min: egui::Pos2 {
-                x: viewport[0] as f32,
-                y: viewport[1] as f32,
-            },
-            max: egui::Pos2 {
-                x: viewport[2] as f32,
-                y: viewport[3] as f32,
-            },
-        }),
-        pixels_per_point: Some(PIXELS_PER_POINT),
-        max_texture_side: Some(max_texture_size as usize),
-        time: None,
-        predicted_dt: 1. / 60.,
-        modifiers: egui::Modifiers::NONE,
-        events: vec![],
-        hovered_files: vec![],
-        dropped_files: vec![],
+/// # Safety
+/// The arguments must be valid for the real `glXSwapBuffersMscOML`, with a
+/// context current on the calling thread.
+#[no_mangle]
+pub unsafe extern "C" fn glXSwapBuffersMscOML(
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    target_msc: i64,
+    divisor: i64,
+    remainder: i64,
+) -> i64 {
+    let real = match GLX.swap_buffers_msc_oml {
+        Some(real) => real,
+        None => return -1,
     };
-
-    let full_output = crate::EGUI_CTX.run(inputs, crate::ui_fn);
-
-    set_required_features();
-
-    let mut program = 0;
-    gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program as *mut _ as *mut _);
-    PAINTER
-        .lock()
-        .unwrap()
-        .adjust_size(viewport[2], viewport[3]);
-    PAINTER.lock().unwrap().paint_jobs(
-        crate::EGUI_CTX.tessellate(full_output.shapes),
-        PIXELS_PER_POINT,
-        full_output.textures_delta,
-    );
-    gl::UseProgram(program);
-
-    (glx.swap_buffers)(dpy, drawable);
-
-    restore_features();
+    super::render_overlay_frame_with(&*GLX, dpy, drawable, |_| {
+        real(dpy, drawable, target_msc, divisor, remainder)
+    })
 }
 
-unsafe fn set_required_features() {
-    CURRENT_FEATURES.lock().unwrap().clear();
-    if gl::IsEnabled(gl::DEPTH_TEST) != 0 {
-        CURRENT_FEATURES
-            .lock()
-            .unwrap()
-            .push((gl::DEPTH_TEST, true));
-        gl::Disable(gl::DEPTH_TEST);
-    }
-    CURRENT_FEATURES
-        .lock()
-        .unwrap()
-        .push((gl::SCISSOR_TEST, gl::IsEnabled(gl::SCISSOR_TEST) != 0));
-    if gl::IsEnabled(gl::SCISSOR_TEST) == 0 {
-        gl::Enable(gl::SCISSOR_TEST);
-    }
-    if gl::IsEnabled(gl::BLEND) == 0 {
-        CURRENT_FEATURES.lock().unwrap().push((gl::BLEND, false));
-        gl::Enable(gl::BLEND);
-    }
-    if gl::IsEnabled(gl::CULL_FACE) != 0 {
-        CURRENT_FEATURES.lock().unwrap().push((gl::CULL_FACE, true));
-        gl::Disable(gl::CULL_FACE);
-    }
-    if gl::IsEnabled(gl::FRAMEBUFFER_SRGB) == 0 {
-        CURRENT_FEATURES
-            .lock()
-            .unwrap()
-            .push((gl::FRAMEBUFFER_SRGB, false));
-        gl::Enable(gl::FRAMEBUFFER_SRGB);
-    }
+/// What `dlsym` hands out for `glXDestroyContext`.
+///
+/// # Safety
+/// Same as `glXDestroyContext`.
+#[no_mangle]
+pub unsafe extern "C" fn overlib_glx_destroy_context(dpy: *mut c_void, ctx: *mut c_void) {
+    glXDestroyContext(dpy, ctx)
 }
 
-unsafe fn restore_features() {
-    for (feature, state) in CURRENT_FEATURES.lock().unwrap().to_vec() {
-        (if state { gl::Enable } else { gl::Disable })(feature);
-    }
+/// # Safety
+/// `dpy` and `ctx` must be valid for the real `glXDestroyContext`.
+#[no_mangle]
+pub unsafe extern "C" fn glXDestroyContext(dpy: *mut c_void, ctx: *mut c_void) {
+    super::destroy_context(&*GLX, dpy, ctx);
+    (GLX.destroy_context)(dpy, ctx)
 }
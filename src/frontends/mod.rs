@@ -1,2 +1,323 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::sync::Mutex;
+
+use glad_gl::gl;
+
+use crate::backends::opengl::painter::Painter;
+use crate::backends::opengl::screenshot::read_back_buffer;
+use crate::backends::opengl::state::GlStateGuard;
+use crate::frame_clock::FrameClock;
+use crate::logging::RendererInfo;
+
 pub mod egl;
 pub mod glx;
+mod textures;
+pub mod vulkan;
+
+use textures::TextureMirror;
+
+struct ContextState {
+    painter: Painter,
+    /// Last drawable the context presented, to make it current again on destruction.
+    drawable: usize,
+    /// `GL_RENDERER` and `GL_VERSION`, for frame logs and benchmark reports.
+    renderer: String,
+    version: String,
+}
+
+unsafe fn gl_string(name: gl::GLenum) -> String {
+    let string = gl::GetString(name);
+    if string.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(string as *const _)
+        .to_string_lossy()
+        .into_owned()
+}
+
+lazy_static! {
+    /// Overlay state, keyed by the GL context its objects belong to.
+    static ref CONTEXTS: Mutex<HashMap<usize, ContextState>> = Mutex::new(HashMap::new());
+    static ref TEXTURES: Mutex<TextureMirror> = Mutex::new(TextureMirror::default());
+    static ref CLOCKS: Mutex<HashMap<usize, FrameClock>> = Mutex::new(HashMap::new());
+    /// Area the overlay covered in the last frame of each drawable.
+    static ref DRAWN_AREAS: Mutex<HashMap<usize, [i32; 4]>> = Mutex::new(HashMap::new());
+}
+
+/// The context the GL function pointers were last loaded for.
+static mut LOADED_CONTEXT: usize = 0;
+
+/// Pixels per egui point, from the configured scale.
+pub(crate) fn pixels_per_point() -> f32 {
+    crate::config::CONFIG.lock().unwrap().scale
+}
+
+/// Display, drawables and context bound to a thread.
+pub struct Binding {
+    pub dpy: *mut c_void,
+    pub draw: *mut c_void,
+    pub read: *mut c_void,
+    pub context: *mut c_void,
+}
+
+/// What a GL windowing API has to provide for the overlay to be drawn on its
+/// drawables, everything else is shared through `render_overlay_frame`.
+pub(crate) trait Frontend {
+    /// What the real swap call returns.
+    type SwapResult;
+
+    const NAME: &'static str;
+
+    /// The context current on the calling thread, null if there is none.
+    unsafe fn current_context(&self) -> *mut c_void;
+
+    unsafe fn current_binding(&self) -> Binding;
+
+    unsafe fn make_current(&self, binding: &Binding) -> bool;
+
+    /// The X window behind `drawable`, to only take input meant for it.
+    fn native_window(&self, _drawable: *mut c_void) -> Option<u64> {
+        None
+    }
+
+    unsafe fn drawable_size(&self, dpy: *mut c_void, drawable: *mut c_void) -> (u32, u32);
+
+    unsafe fn get_proc_address(&self, name: &CStr) -> *mut c_void;
+
+    unsafe fn swap_buffers(&self, dpy: *mut c_void, drawable: *mut c_void) -> Self::SwapResult;
+}
+
+pub fn raw_input(
+    width: u32,
+    height: u32,
+    max_texture_side: usize,
+    clock: &FrameClock,
+) -> egui::RawInput {
+    let (events, modifiers) = crate::input::take();
+    let pixels_per_point = pixels_per_point();
+    egui::RawInput {
+        screen_rect: Some(egui::Rect {
+            min: egui::Pos2 { x: 0., y: 0. },
+            max: egui::Pos2 {
+                x: width as f32 / pixels_per_point,
+                y: height as f32 / pixels_per_point,
+            },
+        }),
+        pixels_per_point: Some(pixels_per_point),
+        max_texture_side: Some(max_texture_side),
+        time: Some(clock.time()),
+        predicted_dt: clock.predicted_dt(),
+        modifiers,
+        events,
+        hovered_files: vec![],
+        dropped_files: vec![],
+    }
+}
+
+unsafe fn load_gl<F: Frontend>(frontend: &F, context: usize) {
+    // entry points may differ between contexts, e.g. when they come from different drivers
+    if LOADED_CONTEXT != context {
+        gl::load(|e| {
+            let name = CString::new(e).unwrap();
+            frontend.get_proc_address(&name) as *const c_void
+        });
+        LOADED_CONTEXT = context;
+    }
+}
+
+/// Area the overlay covers in pixels, as `[x, y, width, height]` with the origin
+/// at the bottom left like EGL damage rectangles.
+fn overlay_damage(width: u32, height: u32) -> Option<[i32; 4]> {
+    let rect = crate::EGUI_CTX.used_rect();
+    if !rect.is_positive() {
+        return None;
+    }
+    let pixels_per_point = crate::EGUI_CTX.pixels_per_point();
+    let min_x = (rect.min.x * pixels_per_point).floor().max(0.) as i32;
+    let min_y = (rect.min.y * pixels_per_point).floor().max(0.) as i32;
+    let max_x = (rect.max.x * pixels_per_point).ceil().min(width as f32) as i32;
+    let max_y = (rect.max.y * pixels_per_point).ceil().min(height as f32) as i32;
+    if max_x <= min_x || max_y <= min_y {
+        return None;
+    }
+    Some([min_x, height as i32 - max_y, max_x - min_x, max_y - min_y])
+}
+
+/// The overlay's area in this frame along with the one of the last frame, whose
+/// pixels are stale once the overlay moved, shrank or was hidden.
+fn damage_since_last_frame(drawable: usize, area: Option<[i32; 4]>) -> Option<[i32; 4]> {
+    let mut drawn_areas = DRAWN_AREAS.lock().unwrap();
+    let last = match area {
+        Some(area) => drawn_areas.insert(drawable, area),
+        None => drawn_areas.remove(&drawable),
+    };
+    match (area, last) {
+        (Some([x, y, w, h]), Some([last_x, last_y, last_w, last_h])) => {
+            let min_x = x.min(last_x);
+            let min_y = y.min(last_y);
+            let max_x = (x + w).max(last_x + last_w);
+            let max_y = (y + h).max(last_y + last_h);
+            Some([min_x, min_y, max_x - min_x, max_y - min_y])
+        }
+        (area, last) => area.or(last),
+    }
+}
+
+/// Draws the overlay on top of `drawable` with the current context, then presents it.
+pub(crate) unsafe fn render_overlay_frame<F: Frontend>(
+    frontend: &F,
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+) -> F::SwapResult {
+    render_overlay_frame_with(frontend, dpy, drawable, |_| {
+        frontend.swap_buffers(dpy, drawable)
+    })
+}
+
+/// Same as `render_overlay_frame` for the other ways of presenting, `present` is
+/// given the area the overlay was drawn on if any.
+pub(crate) unsafe fn render_overlay_frame_with<F: Frontend, R>(
+    frontend: &F,
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+    present: impl FnOnce(Option<[i32; 4]>) -> R,
+) -> R {
+    let damage = draw_overlay(frontend, dpy, drawable);
+    crate::limiter::limit(|| present(damage))
+}
+
+unsafe fn draw_overlay<F: Frontend>(
+    frontend: &F,
+    dpy: *mut c_void,
+    drawable: *mut c_void,
+) -> Option<[i32; 4]> {
+    let mut clocks = CLOCKS.lock().unwrap();
+    let clock = clocks.entry(drawable as usize).or_default();
+    clock.tick();
+    let frame_time = clock.frame_times().back().copied();
+
+    let context = frontend.current_context() as usize;
+    if context == 0 {
+        return None;
+    }
+
+    if let Some(window) = frontend.native_window(drawable) {
+        crate::input::watch_window(window);
+    }
+
+    load_gl(frontend, context);
+
+    let (width, height) = frontend.drawable_size(dpy, drawable);
+
+    let mut max_texture_size: i32 = 0;
+    gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size as *mut i32);
+
+    let full_output = crate::EGUI_CTX.run(
+        raw_input(width, height, max_texture_size as usize, clock),
+        |ctx| crate::ui_fn(ctx, clock),
+    );
+    drop(clocks);
+    let damage = damage_since_last_frame(drawable as usize, overlay_damage(width, height));
+
+    let mut textures = TEXTURES.lock().unwrap();
+    textures.update(&full_output.textures_delta);
+
+    if crate::screenshot::take_request() && width > 0 && height > 0 {
+        // before the overlay is drawn on top
+        crate::screenshot::save(width, height, &read_back_buffer(width, height));
+    }
+
+    let gl_state = GlStateGuard::capture();
+
+    let mut contexts = CONTEXTS.lock().unwrap();
+    let is_new = !contexts.contains_key(&context);
+    let state = contexts.entry(context).or_insert_with(|| ContextState {
+        painter: Painter::new(width, height),
+        drawable: 0,
+        renderer: gl_string(gl::RENDERER),
+        version: gl_string(gl::VERSION),
+    });
+    state.drawable = drawable as usize;
+    state.painter.adjust_size(width as i32, height as i32);
+    state.painter.paint_jobs(
+        crate::EGUI_CTX.tessellate(full_output.shapes),
+        crate::EGUI_CTX.pixels_per_point(),
+        textures.delta_for(&full_output.textures_delta, is_new),
+    );
+    if let Some(frame_time) = frame_time {
+        crate::on_frame(
+            frame_time,
+            &RendererInfo {
+                frontend: F::NAME,
+                gpu: &state.renderer,
+                driver: &state.version,
+                width,
+                height,
+            },
+        );
+    }
+    drop(contexts);
+
+    drop(gl_state);
+
+    textures.free(&full_output.textures_delta.free);
+    drop(textures);
+
+    damage
+}
+
+/// Releases the overlay's objects in `context`, which is about to be destroyed.
+///
+/// The context has to be current for that, so it is bound to the last drawable
+/// it presented and the thread's previous binding is restored afterwards.
+pub(crate) unsafe fn destroy_context<F: Frontend>(
+    frontend: &F,
+    dpy: *mut c_void,
+    context: *mut c_void,
+) {
+    let state = match CONTEXTS.lock().unwrap().remove(&(context as usize)) {
+        Some(state) => state,
+        None => return,
+    };
+    let previous = frontend.current_binding();
+    if previous.context == context {
+        load_gl(frontend, context as usize);
+        drop(state);
+        LOADED_CONTEXT = 0;
+        return;
+    }
+
+    let drawable = state.drawable as *mut c_void;
+    let target = Binding {
+        dpy,
+        draw: drawable,
+        read: drawable,
+        context,
+    };
+    if !frontend.make_current(&target) {
+        eprintln!(
+            "could not make context {:p} current, leaking its overlay",
+            context
+        );
+        std::mem::forget(state);
+        return;
+    }
+    load_gl(frontend, context as usize);
+    drop(state);
+    LOADED_CONTEXT = 0;
+
+    let restored = if previous.context.is_null() {
+        Binding {
+            dpy,
+            draw: std::ptr::null_mut(),
+            read: std::ptr::null_mut(),
+            context: std::ptr::null_mut(),
+        }
+    } else {
+        previous
+    };
+    frontend.make_current(&restored);
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use egui::epaint::ahash::AHashMap;
+use egui::epaint::{ImageData, ImageDelta};
+use egui::{TextureId, TexturesDelta};
+
+/// CPU copy of every texture egui has allocated.
+///
+/// egui only sends a texture once, so a painter created after the first frame
+/// (in a new context) would never see the font atlas. Painters are handed full
+/// images from here instead of the raw deltas.
+#[derive(Default)]
+pub struct TextureMirror {
+    images: HashMap<TextureId, ImageData>,
+}
+
+fn patch<T: Copy>(
+    dst: &mut [T],
+    dst_width: usize,
+    src: &[T],
+    src_size: [usize; 2],
+    pos: [usize; 2],
+) {
+    for y in 0..src_size[1] {
+        let from = y * src_size[0];
+        let to = (pos[1] + y) * dst_width + pos[0];
+        dst[to..to + src_size[0]].copy_from_slice(&src[from..from + src_size[0]]);
+    }
+}
+
+impl TextureMirror {
+    /// Applies the `set` part of a frame's delta.
+    pub fn update(&mut self, delta: &TexturesDelta) {
+        for (id, image_delta) in delta.set.iter() {
+            let pos = match image_delta.pos {
+                Some(pos) => pos,
+                None => {
+                    self.images.insert(*id, image_delta.image.clone());
+                    continue;
+                }
+            };
+            match (self.images.get_mut(id), &image_delta.image) {
+                (Some(ImageData::Color(dst)), ImageData::Color(src)) => {
+                    patch(&mut dst.pixels, dst.size[0], &src.pixels, src.size, pos)
+                }
+                (Some(ImageData::Font(dst)), ImageData::Font(src)) => {
+                    patch(&mut dst.pixels, dst.size[0], &src.pixels, src.size, pos)
+                }
+                _ => eprintln!("partial update of unknown texture {:?}", id),
+            }
+        }
+    }
+
+    /// Forgets the textures egui freed this frame, once every painter is done with them.
+    pub fn free(&mut self, free: &[TextureId]) {
+        for id in free {
+            self.images.remove(id);
+        }
+    }
+
+    /// The delta a painter needs this frame, `everything` being set when it has
+    /// never seen a texture before.
+    pub fn delta_for(&self, delta: &TexturesDelta, everything: bool) -> TexturesDelta {
+        let mut set = AHashMap::default();
+        for (id, image) in self.images.iter() {
+            if everything || delta.set.contains_key(id) {
+                set.insert(*id, ImageDelta::full(image.clone()));
+            }
+        }
+        TexturesDelta {
+            set,
+            free: delta.free.clone(),
+        }
+    }
+}
@@ -0,0 +1,641 @@
+// Implicit Vulkan layer, see `overlib_vulkan.json` at the root of the repository.
+//
+// To try it with the lavapipe software driver, copy the manifest into
+// `~/.local/share/vulkan/implicit_layer.d/`, make sure `liboverlay.so` can be
+// found by the dynamic loader and run something like:
+// `ENABLE_OVERLIB_VULKAN=1 VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json vkcube`
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+use ash::vk::Handle;
+
+use crate::backends::vulkan::painter::{Painter, SetDeviceLoaderData};
+use crate::frame_clock::FrameClock;
+use crate::logging::RendererInfo;
+
+lazy_static! {
+    static ref INSTANCES: Mutex<HashMap<usize, InstanceData>> = Mutex::new(HashMap::new());
+    static ref DEVICES: Mutex<HashMap<usize, DeviceData>> = Mutex::new(HashMap::new());
+}
+
+/// Whether a failure to draw was reported, it likely repeats every frame.
+static PAINT_FAILED: AtomicBool = AtomicBool::new(false);
+
+// the following definitions come from the loader's `vk_layer.h`, which ash doesn't provide
+
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VkLayerFunction {
+    LayerLinkInfo = 0,
+    LoaderDataCallback = 1,
+    LoaderLayerCreateDeviceCallback = 2,
+    LoaderFeatures = 3,
+}
+
+#[repr(C)]
+struct VkLayerInstanceLink {
+    next: *mut VkLayerInstanceLink,
+    next_get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+    next_get_physical_device_proc_addr: *const c_void,
+}
+
+#[repr(C)]
+struct VkLayerInstanceCreateInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    function: VkLayerFunction,
+    // this is a union in C, but we only ever touch the `pLayerInfo` member
+    layer_info: *mut VkLayerInstanceLink,
+    _union_padding: *const c_void,
+}
+
+#[repr(C)]
+struct VkLayerDeviceLink {
+    next: *mut VkLayerDeviceLink,
+    next_get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+    next_get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+}
+
+#[repr(C)]
+struct VkLayerDeviceCreateInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    function: VkLayerFunction,
+    u: VkLayerDeviceCreateInfoUnion,
+}
+
+/// Which member is set depends on `function`.
+#[repr(C)]
+union VkLayerDeviceCreateInfoUnion {
+    layer_info: *mut VkLayerDeviceLink,
+    set_device_loader_data: SetDeviceLoaderData,
+}
+
+struct InstanceData {
+    instance: ash::Instance,
+    get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+}
+
+struct DeviceData {
+    device: ash::Device,
+    get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+    swapchain_fn: vk::KhrSwapchainFn,
+    queue_families: HashMap<vk::Queue, u32>,
+    /// `None` when the device has no graphics queue to draw the overlay with.
+    /// Presenting only holds its lock, other devices aren't kept waiting.
+    overlay: Option<Arc<Mutex<Overlay>>>,
+}
+
+struct Overlay {
+    painter: Painter,
+    swapchains: HashMap<vk::SwapchainKHR, SwapchainData>,
+    /// Whether the painter was given every texture, see `TextureMirror`.
+    has_textures: bool,
+    max_texture_side: usize,
+    queue_family: u32,
+    queue: vk::Queue,
+    /// Device name and API version, for frame logs and benchmark reports.
+    gpu: String,
+    driver: String,
+}
+
+struct SwapchainData {
+    extent: vk::Extent2D,
+    clock: FrameClock,
+}
+
+/// Dispatchable handles all start with a pointer to the loader's dispatch table,
+/// which is shared between an instance and its physical devices, and between a
+/// device and its queues.
+unsafe fn dispatch_key<T: Handle>(handle: T) -> usize {
+    *(handle.as_raw() as *const usize)
+}
+
+unsafe fn find_instance_link(
+    create_info: *const vk::InstanceCreateInfo,
+) -> *mut VkLayerInstanceCreateInfo {
+    let mut p = (*create_info).p_next as *mut VkLayerInstanceCreateInfo;
+    while !p.is_null() {
+        if (*p).s_type == vk::StructureType::LOADER_INSTANCE_CREATE_INFO
+            && (*p).function == VkLayerFunction::LayerLinkInfo
+        {
+            return p;
+        }
+        p = (*p).p_next as *mut _;
+    }
+    p
+}
+
+unsafe fn find_device_link(
+    create_info: *const vk::DeviceCreateInfo,
+    function: VkLayerFunction,
+) -> *mut VkLayerDeviceCreateInfo {
+    let mut p = (*create_info).p_next as *mut VkLayerDeviceCreateInfo;
+    while !p.is_null() {
+        if (*p).s_type == vk::StructureType::LOADER_DEVICE_CREATE_INFO && (*p).function == function
+        {
+            return p;
+        }
+        p = (*p).p_next as *mut _;
+    }
+    p
+}
+
+/// The hooks `vkGetInstanceProcAddr` gives, which include the device level ones.
+unsafe fn hooked_proc(name: &str) -> vk::PFN_vkVoidFunction {
+    let f: *const c_void = match name {
+        "vkGetInstanceProcAddr" => overlib_vk_get_instance_proc_addr as _,
+        "vkCreateInstance" => create_instance as _,
+        "vkDestroyInstance" => destroy_instance as _,
+        "vkCreateDevice" => create_device as _,
+        _ => return hooked_device_proc(name),
+    };
+    std::mem::transmute(f)
+}
+
+/// The hooks `vkGetDeviceProcAddr` gives, it must return nothing for instance level
+/// functions.
+unsafe fn hooked_device_proc(name: &str) -> vk::PFN_vkVoidFunction {
+    let f: *const c_void = match name {
+        "vkGetDeviceProcAddr" => overlib_vk_get_device_proc_addr as _,
+        "vkDestroyDevice" => destroy_device as _,
+        "vkGetDeviceQueue" => get_device_queue as _,
+        "vkGetDeviceQueue2" => get_device_queue2 as _,
+        "vkCreateSwapchainKHR" => create_swapchain as _,
+        "vkDestroySwapchainKHR" => destroy_swapchain as _,
+        "vkQueuePresentKHR" => queue_present as _,
+        _ => return None,
+    };
+    std::mem::transmute(f)
+}
+
+/// The layer's `vkGetInstanceProcAddr`.
+///
+/// # Safety
+/// `p_name` must be a valid nul-terminated string and `instance` null or an
+/// instance created through the layer.
+#[no_mangle]
+pub unsafe extern "system" fn overlib_vk_get_instance_proc_addr(
+    instance: vk::Instance,
+    p_name: *const c_char,
+) -> vk::PFN_vkVoidFunction {
+    let name = std::ffi::CStr::from_ptr(p_name).to_str().unwrap_or("");
+    if let Some(f) = hooked_proc(name) {
+        return Some(f);
+    }
+    if instance == vk::Instance::null() {
+        return None;
+    }
+
+    let gipa = match INSTANCES.lock().unwrap().get(&dispatch_key(instance)) {
+        Some(data) => data.get_instance_proc_addr,
+        None => return None,
+    };
+    gipa(instance, p_name)
+}
+
+/// The layer's `vkGetDeviceProcAddr`.
+///
+/// # Safety
+/// `p_name` must be a valid nul-terminated string and `device` a device
+/// created through the layer.
+#[no_mangle]
+pub unsafe extern "system" fn overlib_vk_get_device_proc_addr(
+    device: vk::Device,
+    p_name: *const c_char,
+) -> vk::PFN_vkVoidFunction {
+    let name = std::ffi::CStr::from_ptr(p_name).to_str().unwrap_or("");
+    if let Some(f) = hooked_device_proc(name) {
+        return Some(f);
+    }
+
+    let gdpa = match DEVICES.lock().unwrap().get(&dispatch_key(device)) {
+        Some(data) => data.get_device_proc_addr,
+        None => return None,
+    };
+    gdpa(device, p_name)
+}
+
+unsafe extern "system" fn create_instance(
+    p_create_info: *const vk::InstanceCreateInfo,
+    p_allocator: *const vk::AllocationCallbacks,
+    p_instance: *mut vk::Instance,
+) -> vk::Result {
+    let link = find_instance_link(p_create_info);
+    if link.is_null() {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    }
+    let gipa = (*(*link).layer_info).next_get_instance_proc_addr;
+    // the next layer expects to find its own link info
+    (*link).layer_info = (*(*link).layer_info).next;
+
+    let next_create_instance: vk::PFN_vkCreateInstance =
+        match gipa(vk::Instance::null(), c"vkCreateInstance".as_ptr()) {
+            Some(f) => {
+                std::mem::transmute::<unsafe extern "system" fn(), vk::PFN_vkCreateInstance>(f)
+            }
+            None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+        };
+    let res = next_create_instance(p_create_info, p_allocator, p_instance);
+    if res != vk::Result::SUCCESS {
+        return res;
+    }
+
+    let instance = ash::Instance::load(
+        &vk::StaticFn {
+            get_instance_proc_addr: gipa,
+        },
+        *p_instance,
+    );
+    INSTANCES.lock().unwrap().insert(
+        dispatch_key(*p_instance),
+        InstanceData {
+            instance,
+            get_instance_proc_addr: gipa,
+        },
+    );
+
+    res
+}
+
+unsafe extern "system" fn destroy_instance(
+    instance: vk::Instance,
+    p_allocator: *const vk::AllocationCallbacks,
+) {
+    if let Some(data) = INSTANCES.lock().unwrap().remove(&dispatch_key(instance)) {
+        (data.instance.fp_v1_0().destroy_instance)(instance, p_allocator);
+    }
+}
+
+unsafe extern "system" fn create_device(
+    physical_device: vk::PhysicalDevice,
+    p_create_info: *const vk::DeviceCreateInfo,
+    p_allocator: *const vk::AllocationCallbacks,
+    p_device: *mut vk::Device,
+) -> vk::Result {
+    let link = find_device_link(p_create_info, VkLayerFunction::LayerLinkInfo);
+    let callback = find_device_link(p_create_info, VkLayerFunction::LoaderDataCallback);
+    if link.is_null() || callback.is_null() {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    }
+    let gipa = (*(*link).u.layer_info).next_get_instance_proc_addr;
+    let gdpa = (*(*link).u.layer_info).next_get_device_proc_addr;
+    (*link).u.layer_info = (*(*link).u.layer_info).next;
+    // dispatchable objects made by the layer get their dispatch table through it
+    let set_device_loader_data = (*callback).u.set_device_loader_data;
+
+    let instances = INSTANCES.lock().unwrap();
+    let instance = match instances.get(&dispatch_key(physical_device)) {
+        Some(data) => &data.instance,
+        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+    };
+    let mut instance_fn = instance.fp_v1_0().clone();
+    // device level functions must come from the next layer in the device chain
+    instance_fn.get_device_proc_addr = gdpa;
+
+    let next_create_device: vk::PFN_vkCreateDevice =
+        match gipa(instance.handle(), c"vkCreateDevice".as_ptr()) {
+            Some(f) => {
+                std::mem::transmute::<unsafe extern "system" fn(), vk::PFN_vkCreateDevice>(f)
+            }
+            None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+        };
+    let res = next_create_device(physical_device, p_create_info, p_allocator, p_device);
+    if res != vk::Result::SUCCESS {
+        return res;
+    }
+
+    let device = ash::Device::load(&instance_fn, *p_device);
+    let swapchain_fn =
+        vk::KhrSwapchainFn::load(|name| std::mem::transmute(gdpa(*p_device, name.as_ptr())));
+
+    // the overlay is drawn with the first graphics capable queue family the
+    // application asked for
+    let families = instance.get_physical_device_queue_family_properties(physical_device);
+    let queue_infos = std::slice::from_raw_parts(
+        (*p_create_info).p_queue_create_infos,
+        (*p_create_info).queue_create_info_count as usize,
+    );
+    let graphics = queue_infos.iter().find(|q| {
+        families[q.queue_family_index as usize]
+            .queue_flags
+            .contains(vk::QueueFlags::GRAPHICS)
+    });
+    if graphics.is_none() {
+        eprintln!("overlib: no graphics queue on this device, the overlay is disabled");
+    }
+    let overlay = graphics.and_then(|q| {
+        let memory_properties = instance.get_physical_device_memory_properties(physical_device);
+        let painter = match Painter::new(
+            device.clone(),
+            set_device_loader_data,
+            memory_properties,
+            q.queue_family_index,
+        ) {
+            Ok(painter) => painter,
+            Err(e) => {
+                // the device still works, it only goes without the overlay
+                eprintln!("overlib: could not create the overlay: {}", e);
+                return None;
+            }
+        };
+        let queue = device.get_device_queue(q.queue_family_index, 0);
+        // queues obtained below the loader lack a dispatch table
+        let res = set_device_loader_data(*p_device, queue.as_raw() as *mut c_void);
+        if res != vk::Result::SUCCESS {
+            eprintln!("overlib: could not set up the overlay queue: {}", res);
+            return None;
+        }
+
+        let properties = instance.get_physical_device_properties(physical_device);
+        Some(Arc::new(Mutex::new(Overlay {
+            painter,
+            swapchains: HashMap::new(),
+            has_textures: false,
+            max_texture_side: properties.limits.max_image_dimension2_d as usize,
+            queue_family: q.queue_family_index,
+            queue,
+            gpu: CStr::from_ptr(properties.device_name.as_ptr())
+                .to_string_lossy()
+                .into_owned(),
+            driver: format!(
+                "Vulkan {}.{}.{}",
+                vk::api_version_major(properties.api_version),
+                vk::api_version_minor(properties.api_version),
+                vk::api_version_patch(properties.api_version)
+            ),
+        })))
+    });
+
+    DEVICES.lock().unwrap().insert(
+        dispatch_key(*p_device),
+        DeviceData {
+            device,
+            get_device_proc_addr: gdpa,
+            swapchain_fn,
+            queue_families: HashMap::new(),
+            overlay,
+        },
+    );
+
+    res
+}
+
+unsafe extern "system" fn destroy_device(
+    device: vk::Device,
+    p_allocator: *const vk::AllocationCallbacks,
+) {
+    if let Some(data) = DEVICES.lock().unwrap().remove(&dispatch_key(device)) {
+        let destroy = data.device.fp_v1_0().destroy_device;
+        // the painter must release its resources while the device is still alive
+        drop(data);
+        destroy(device, p_allocator);
+    }
+}
+
+/// Called for a device the layer didn't create. Without its dispatch table there is
+/// no next layer to call, but panicking here would abort the application.
+fn unknown_device() {
+    eprintln!("overlib: called for a device created without the layer");
+}
+
+unsafe extern "system" fn get_device_queue(
+    device: vk::Device,
+    queue_family_index: u32,
+    queue_index: u32,
+    p_queue: *mut vk::Queue,
+) {
+    let mut devices = DEVICES.lock().unwrap();
+    let data = match devices.get_mut(&dispatch_key(device)) {
+        Some(data) => data,
+        None => return unknown_device(),
+    };
+    (data.device.fp_v1_0().get_device_queue)(device, queue_family_index, queue_index, p_queue);
+    data.queue_families.insert(*p_queue, queue_family_index);
+}
+
+unsafe extern "system" fn get_device_queue2(
+    device: vk::Device,
+    p_queue_info: *const vk::DeviceQueueInfo2,
+    p_queue: *mut vk::Queue,
+) {
+    let mut devices = DEVICES.lock().unwrap();
+    let data = match devices.get_mut(&dispatch_key(device)) {
+        Some(data) => data,
+        None => return unknown_device(),
+    };
+    (data.device.fp_v1_1().get_device_queue2)(device, p_queue_info, p_queue);
+    data.queue_families
+        .insert(*p_queue, (*p_queue_info).queue_family_index);
+}
+
+unsafe extern "system" fn create_swapchain(
+    device: vk::Device,
+    p_create_info: *const vk::SwapchainCreateInfoKHR,
+    p_allocator: *const vk::AllocationCallbacks,
+    p_swapchain: *mut vk::SwapchainKHR,
+) -> vk::Result {
+    let mut devices = DEVICES.lock().unwrap();
+    let data = match devices.get_mut(&dispatch_key(device)) {
+        Some(data) => data,
+        None => {
+            unknown_device();
+            return vk::Result::ERROR_INITIALIZATION_FAILED;
+        }
+    };
+
+    // the overlay is rendered straight into the swapchain images
+    let mut create_info = *p_create_info;
+    create_info.image_usage |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
+
+    let res =
+        (data.swapchain_fn.create_swapchain_khr)(device, &create_info, p_allocator, p_swapchain);
+    if res != vk::Result::SUCCESS {
+        return res;
+    }
+
+    if let Some(overlay) = &data.overlay {
+        let mut overlay = overlay.lock().unwrap();
+        let images = match swapchain_images(&data.swapchain_fn, device, *p_swapchain) {
+            Ok(images) => images,
+            Err(e) => {
+                // the swapchain still works, it only goes without the overlay
+                eprintln!("overlib: could not get the swapchain images: {}", e);
+                return res;
+            }
+        };
+        if let Err(e) = overlay.painter.add_swapchain(
+            *p_swapchain,
+            create_info.image_format,
+            create_info.image_extent,
+            &images,
+        ) {
+            eprintln!("overlib: could not draw on the swapchain: {}", e);
+            return res;
+        }
+        overlay.swapchains.insert(
+            *p_swapchain,
+            SwapchainData {
+                extent: create_info.image_extent,
+                clock: FrameClock::default(),
+            },
+        );
+    }
+
+    res
+}
+
+unsafe fn swapchain_images(
+    swapchain_fn: &vk::KhrSwapchainFn,
+    device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+) -> Result<Vec<vk::Image>, vk::Result> {
+    let mut count = 0;
+    (swapchain_fn.get_swapchain_images_khr)(device, swapchain, &mut count, std::ptr::null_mut())
+        .result()?;
+    let mut images = vec![vk::Image::null(); count as usize];
+    (swapchain_fn.get_swapchain_images_khr)(device, swapchain, &mut count, images.as_mut_ptr())
+        .result()?;
+    images.truncate(count as usize);
+    Ok(images)
+}
+
+unsafe extern "system" fn destroy_swapchain(
+    device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    p_allocator: *const vk::AllocationCallbacks,
+) {
+    let mut devices = DEVICES.lock().unwrap();
+    let data = match devices.get_mut(&dispatch_key(device)) {
+        Some(data) => data,
+        None => return unknown_device(),
+    };
+    if let Some(overlay) = &data.overlay {
+        let mut overlay = overlay.lock().unwrap();
+        overlay.swapchains.remove(&swapchain);
+        overlay.painter.remove_swapchain(swapchain);
+    }
+    (data.swapchain_fn.destroy_swapchain_khr)(device, swapchain, p_allocator);
+}
+
+unsafe extern "system" fn queue_present(
+    queue: vk::Queue,
+    p_present_info: *const vk::PresentInfoKHR,
+) -> vk::Result {
+    // queues share the dispatch table of the device they come from
+    let devices = DEVICES.lock().unwrap();
+    let data = match devices.get(&dispatch_key(queue)) {
+        Some(data) => data,
+        None => {
+            unknown_device();
+            return vk::Result::ERROR_DEVICE_LOST;
+        }
+    };
+    let queue_present_khr = data.swapchain_fn.queue_present_khr;
+    let overlay = match &data.overlay {
+        Some(overlay) => overlay.clone(),
+        None => return queue_present_khr(queue, p_present_info),
+    };
+    let queue_family = data.queue_families.get(&queue).copied();
+    // the ui and painting only need the overlay, other devices can go on meanwhile
+    drop(devices);
+    let mut overlay = overlay.lock().unwrap();
+    let overlay = &mut *overlay;
+
+    // prefer the presenting queue, the application may be using ours from another thread
+    let draw_queue = if queue_family == Some(overlay.queue_family) {
+        queue
+    } else {
+        overlay.queue
+    };
+
+    let present_info = *p_present_info;
+    let swapchains = std::slice::from_raw_parts(
+        present_info.p_swapchains,
+        present_info.swapchain_count as usize,
+    );
+    let image_indices = std::slice::from_raw_parts(
+        present_info.p_image_indices,
+        present_info.swapchain_count as usize,
+    );
+    let mut wait_semaphores = std::slice::from_raw_parts(
+        present_info.p_wait_semaphores,
+        present_info.wait_semaphore_count as usize,
+    )
+    .to_vec();
+    let mut overlay_semaphores = vec![];
+
+    for (swapchain, image_index) in swapchains.iter().zip(image_indices) {
+        let swapchain_data = match overlay.swapchains.get_mut(swapchain) {
+            Some(s) => s,
+            None => continue,
+        };
+        let extent = swapchain_data.extent;
+        let clock = &mut swapchain_data.clock;
+        clock.tick();
+        if let Some(frame_time) = clock.frame_times().back() {
+            crate::on_frame(
+                *frame_time,
+                &RendererInfo {
+                    frontend: "vulkan",
+                    gpu: &overlay.gpu,
+                    driver: &overlay.driver,
+                    width: extent.width,
+                    height: extent.height,
+                },
+            );
+        }
+
+        let inputs = super::raw_input(extent.width, extent.height, overlay.max_texture_side, clock);
+
+        let full_output = crate::EGUI_CTX.run(inputs, |ctx| crate::ui_fn(ctx, clock));
+
+        // shared with the other devices and GL contexts, which may see textures first
+        let mut textures = super::TEXTURES.lock().unwrap();
+        textures.update(&full_output.textures_delta);
+        let delta = textures.delta_for(&full_output.textures_delta, !overlay.has_textures);
+        let painted = overlay.painter.paint_jobs(
+            draw_queue,
+            *swapchain,
+            *image_index,
+            &wait_semaphores,
+            crate::EGUI_CTX.tessellate(full_output.shapes),
+            crate::EGUI_CTX.pixels_per_point(),
+            delta,
+        );
+        textures.free(&full_output.textures_delta.free);
+        drop(textures);
+        match painted {
+            Ok(semaphore) => {
+                overlay.has_textures = true;
+                overlay_semaphores.push(semaphore);
+                // binary semaphores can only be waited on once
+                wait_semaphores.clear();
+            }
+            Err(e) => {
+                // the image is presented as the application left it, and the
+                // textures that went missing are sent again next frame
+                if !PAINT_FAILED.swap(true, Ordering::Relaxed) {
+                    eprintln!("overlib: could not draw the overlay: {}", e);
+                }
+                overlay.has_textures = false;
+            }
+        }
+    }
+    // if nothing was drawn, presentation still has to wait for the application
+    overlay_semaphores.append(&mut wait_semaphores);
+
+    let present_info = vk::PresentInfoKHR {
+        wait_semaphore_count: overlay_semaphores.len() as u32,
+        p_wait_semaphores: overlay_semaphores.as_ptr(),
+        ..present_info
+    };
+    queue_present_khr(queue, &present_info)
+}
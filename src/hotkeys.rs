@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::config::{Config, CONFIG};
+
+lazy_static! {
+    static ref HOTKEYS: Mutex<Hotkeys> = Mutex::new(Hotkeys::new(&CONFIG.lock().unwrap()));
+}
+
+/// What a hotkey does, dispatched once per frame by `crate::on_frame`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    ToggleOverlay,
+    ToggleLogging,
+    ToggleInteractive,
+    StopBenchmark,
+    Screenshot,
+    CyclePosition,
+    ReloadConfig,
+}
+
+/// Every action with its name in the configuration and its default binding.
+const ACTIONS: [(Action, &str, &str); 7] = [
+    (Action::ToggleOverlay, "toggle_overlay", "Shift_R+F12"),
+    (Action::ToggleLogging, "toggle_logging", "Shift_L+F2"),
+    (
+        Action::ToggleInteractive,
+        "toggle_interactive",
+        "Shift_L+F3",
+    ),
+    (Action::StopBenchmark, "stop_benchmark", "Shift_L+F12"),
+    (Action::Screenshot, "screenshot", "Shift_L+F10"),
+    (Action::CyclePosition, "cycle_position", "Shift_R+F11"),
+    (Action::ReloadConfig, "reload_config", "Shift_L+F4"),
+];
+
+/// Keys that all have to be held, the last one pressed triggers the action.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Binding {
+    keysyms: Vec<u32>,
+    text: String,
+}
+
+impl std::str::FromStr for Binding {
+    type Err = String;
+
+    /// Parses key names joined by `+`, e.g. `Shift_R+F12`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keysyms = s
+            .split('+')
+            .map(|name| keysym_from_name(name.trim()).ok_or(format!("unknown key {:?}", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keysyms.is_empty() {
+            return Err("empty binding".to_string());
+        }
+        Ok(Binding {
+            keysyms,
+            text: s.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(a, ..)| *a)
+    }
+}
+
+/// Watches the keys the application receives and turns bindings into actions.
+pub struct Hotkeys {
+    bindings: Vec<(Action, Binding)>,
+    held: HashSet<u32>,
+    triggered: Vec<Action>,
+}
+
+impl Hotkeys {
+    pub fn new(config: &Config) -> Self {
+        let mut hotkeys = Hotkeys {
+            bindings: vec![],
+            held: HashSet::new(),
+            triggered: vec![],
+        };
+        hotkeys.configure(config);
+        hotkeys
+    }
+
+    /// Default bindings, replaced by the configuration then by `OVERLIB_HOTKEY_<ACTION>`,
+    /// e.g. `OVERLIB_HOTKEY_TOGGLE_OVERLAY=Shift_R+F12`. An empty value unbinds the action.
+    pub fn configure(&mut self, config: &Config) {
+        self.bindings.clear();
+        for (action, name, default) in ACTIONS {
+            self.bind(action, default).unwrap();
+            if let Some((_, binding)) = config.hotkeys.iter().find(|(a, _)| *a == action) {
+                // checked when the configuration was read
+                self.bind(action, binding).unwrap();
+            }
+            let var = format!("OVERLIB_HOTKEY_{}", name.to_uppercase());
+            if let Ok(binding) = std::env::var(&var) {
+                if let Err(e) = self.bind(action, &binding) {
+                    eprintln!("invalid {}: {}", var, e);
+                }
+            }
+        }
+    }
+
+    /// Replaces the binding of `action`, an empty `binding` removes it.
+    pub fn bind(&mut self, action: Action, binding: &str) -> Result<(), String> {
+        let binding = match binding.trim() {
+            "" => None,
+            binding => Some(binding.parse::<Binding>()?),
+        };
+        self.bindings.retain(|(a, _)| *a != action);
+        if let Some(binding) = binding {
+            self.bindings.push((action, binding));
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, keysym: u32, pressed: bool) {
+        let keysym = normalize(keysym);
+        if !pressed {
+            self.held.remove(&keysym);
+            return;
+        }
+        // auto-repeat sends presses without releases in between
+        if !self.held.insert(keysym) {
+            return;
+        }
+        for (action, binding) in &self.bindings {
+            if binding.keysyms.contains(&keysym)
+                && binding.keysyms.iter().all(|k| self.held.contains(k))
+            {
+                self.triggered.push(*action);
+            }
+        }
+    }
+}
+
+pub fn configure(config: &Config) {
+    HOTKEYS.lock().unwrap().configure(config);
+}
+
+/// Feeds a key the application received.
+pub fn key(keysym: u32, pressed: bool) {
+    HOTKEYS.lock().unwrap().key(keysym, pressed);
+}
+
+/// Forgets held keys, for when the application loses the keyboard and won't see them released.
+pub fn release_all() {
+    HOTKEYS.lock().unwrap().held.clear();
+}
+
+/// Actions triggered since the last call.
+pub fn take() -> Vec<Action> {
+    std::mem::take(&mut HOTKEYS.lock().unwrap().triggered)
+}
+
+/// Binding of `action` as written in the configuration, if it has one.
+pub fn binding(action: Action) -> Option<String> {
+    HOTKEYS
+        .lock()
+        .unwrap()
+        .bindings
+        .iter()
+        .find(|(a, _)| *a == action)
+        .map(|(_, b)| b.to_string())
+}
+
+/// Keysyms of letters depend on Shift, bindings use the lowercase ones.
+fn normalize(keysym: u32) -> u32 {
+    match keysym {
+        0x41..=0x5a => keysym + 0x20,
+        _ => keysym,
+    }
+}
+
+/// Keysym of an X key name, for the keys that make sense in bindings.
+fn keysym_from_name(name: &str) -> Option<u32> {
+    const NAMED: [(&str, u32); 30] = [
+        ("Shift_L", 0xffe1),
+        ("Shift_R", 0xffe2),
+        ("Control_L", 0xffe3),
+        ("Control_R", 0xffe4),
+        ("Alt_L", 0xffe9),
+        ("Alt_R", 0xffea),
+        ("Super_L", 0xffeb),
+        ("Super_R", 0xffec),
+        ("BackSpace", 0xff08),
+        ("Tab", 0xff09),
+        ("Return", 0xff0d),
+        ("Pause", 0xff13),
+        ("Scroll_Lock", 0xff14),
+        ("Escape", 0xff1b),
+        ("Home", 0xff50),
+        ("Left", 0xff51),
+        ("Up", 0xff52),
+        ("Right", 0xff53),
+        ("Down", 0xff54),
+        ("Prior", 0xff55),
+        ("Page_Up", 0xff55),
+        ("Next", 0xff56),
+        ("Page_Down", 0xff56),
+        ("End", 0xff57),
+        ("Print", 0xff61),
+        ("Insert", 0xff63),
+        ("Delete", 0xffff),
+        ("KP_Add", 0xffab),
+        ("KP_Subtract", 0xffad),
+        ("space", 0x20),
+    ];
+
+    if let Some((_, keysym)) = NAMED.iter().find(|(n, _)| *n == name) {
+        return Some(*keysym);
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse::<u32>().ok()) {
+        return (1..=35).contains(&n).then(|| 0xffbd + n);
+    }
+    match name.as_bytes() {
+        [c @ (b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9')] => Some(normalize(*c as u32)),
+        _ => None,
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use egui::plot::{Line, Plot, Value, Values};
+
+use crate::config::Config;
+use crate::config::CONFIG;
+use crate::frame_clock::FrameStats;
+use crate::widgets::{FrameInfo, OverlayWidget};
+
+/// How far back the current FPS is averaged.
+const FPS_WINDOW_SECS: f64 = 0.5;
+
+/// Frames shown in the frame-time graph.
+const GRAPH_FRAMES: usize = 200;
+
+/// Corners the window goes through with `cycle_position`, and its offset from them.
+const POSITIONS: [(egui::Align2, egui::Vec2); 4] = [
+    (egui::Align2::LEFT_TOP, egui::vec2(8., 8.)),
+    (egui::Align2::RIGHT_TOP, egui::vec2(-8., 8.)),
+    (egui::Align2::RIGHT_BOTTOM, egui::vec2(-8., -8.)),
+    (egui::Align2::LEFT_BOTTOM, egui::vec2(8., -8.)),
+];
+
+lazy_static! {
+    pub static ref HUD: Mutex<Hud> = Mutex::new(Hud::default());
+}
+
+/// Whether and where the overlay is drawn.
+#[derive(Default)]
+pub struct Hud {
+    hidden: bool,
+    /// Index in `POSITIONS` once moved with the hotkey, the configured one until then.
+    position: Option<usize>,
+}
+
+impl Hud {
+    pub fn is_visible(&self) -> bool {
+        !self.hidden
+    }
+
+    pub fn toggle(&mut self) {
+        self.hidden = !self.hidden;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.hidden = !visible;
+    }
+
+    fn position(&self, config: &Config) -> usize {
+        self.position.unwrap_or_else(|| {
+            POSITIONS
+                .iter()
+                .position(|(align, _)| *align == config.position)
+                .unwrap_or(0)
+        })
+    }
+
+    /// The corner below or above the HUD's one and its offset, for other windows
+    /// to stay out of the HUD's way.
+    pub fn other_corner(&self, config: &Config) -> (egui::Align2, egui::Vec2) {
+        // the corners on the same side are at both ends of `POSITIONS`
+        POSITIONS[POSITIONS.len() - 1 - self.position(config)]
+    }
+
+    /// The corner beside the HUD's one on the same edge, so windows there stay out
+    /// of the way of both the HUD and the `other_corner` ones.
+    pub fn corner_beside(&self, config: &Config) -> (egui::Align2, egui::Vec2) {
+        // the corners of each edge are pairs in `POSITIONS`
+        POSITIONS[self.position(config) ^ 1]
+    }
+
+    pub fn cycle_position(&mut self, config: &Config) {
+        self.position = Some((self.position(config) + 1) % POSITIONS.len());
+    }
+
+    /// Goes back to the configured position.
+    pub fn configure(&mut self) {
+        self.position = None;
+    }
+}
+
+/// The FPS and frame time window, in the corner `HUD` is at.
+pub struct Widget;
+
+impl OverlayWidget for Widget {
+    fn name(&self) -> &str {
+        "hud"
+    }
+
+    fn ui(&mut self, ctx: &egui::Context, frame: &FrameInfo) {
+        show(
+            ctx,
+            &HUD.lock().unwrap(),
+            &CONFIG.lock().unwrap(),
+            frame.frame_times,
+        );
+    }
+}
+
+/// Draws the configured parts of the HUD in the corner `hud` is at.
+fn show(ctx: &egui::Context, hud: &Hud, config: &Config, frame_times: &VecDeque<Duration>) {
+    let mut window_frames = 0;
+    let mut window_secs = 0.;
+    for frame_time in frame_times.iter().rev() {
+        if window_secs >= FPS_WINDOW_SECS {
+            break;
+        }
+        window_frames += 1;
+        window_secs += frame_time.as_secs_f64();
+    }
+
+    egui::Window::new("overlib")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(
+            POSITIONS[hud.position(config)].0,
+            POSITIONS[hud.position(config)].1,
+        )
+        .show(ctx, |ui| {
+            if window_frames == 0 {
+                ui.label("waiting for frames");
+                return;
+            }
+            if config.fps {
+                ui.heading(format!("{:.0} FPS", window_frames as f64 / window_secs));
+            }
+
+            if let Some(stats) = FrameStats::new(frame_times).filter(|_| config.frametime) {
+                egui::Grid::new("overlib_stats").show(ui, |ui| {
+                    ui.label("average");
+                    ui.label(format!("{:.0} FPS", stats.avg_fps));
+                    ui.end_row();
+                    ui.label("frame time");
+                    ui.label(format!(
+                        "{:.2} ms",
+                        stats.avg_frame_time.as_secs_f64() * 1000.
+                    ));
+                    ui.end_row();
+                    ui.label("1% low");
+                    ui.label(format!("{:.0} FPS", stats.low_1));
+                    ui.end_row();
+                    ui.label("0.1% low");
+                    ui.label(format!("{:.0} FPS", stats.low_0_1));
+                    ui.end_row();
+                });
+            }
+
+            if !config.frame_timing {
+                return;
+            }
+            let skip = frame_times.len().saturating_sub(GRAPH_FRAMES);
+            let points = frame_times
+                .iter()
+                .skip(skip)
+                .enumerate()
+                .map(|(i, t)| Value::new(i as f64, t.as_secs_f64() * 1000.));
+            Plot::new("overlib_frame_times")
+                .height(60.)
+                .width(200.)
+                .include_x(0.)
+                .include_x(GRAPH_FRAMES as f64)
+                .include_y(0.)
+                .show_axes([false, true])
+                .show_x(false)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .allow_boxed_zoom(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(
+                        Line::new(Values::from_values_iter(points))
+                            .color(config.frametime_color)
+                            .name("ms"),
+                    )
+                });
+        });
+}
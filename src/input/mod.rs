@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+mod wayland;
+mod x11;
+
+lazy_static! {
+    static ref INPUT: Mutex<Input> = Mutex::new(Input::default());
+}
+
+/// Events kept when the overlay isn't drawn for a while.
+const MAX_EVENTS: usize = 1024;
+
+/// Points scrolled per wheel notch.
+const SCROLL_STEP: f32 = 50.;
+
+/// Input collected by the windowing system hooks until the next overlay frame.
+#[derive(Default)]
+struct Input {
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    pointer: egui::Pos2,
+    /// Windows the overlay is drawn on, events of any window are taken while it is empty.
+    windows: HashSet<u64>,
+    /// Events only reach egui in interactive mode, and those it wants are hidden
+    /// from the application.
+    interactive: bool,
+    wants_pointer: bool,
+    wants_keyboard: bool,
+    /// Keys and buttons down, by keycode and button code, so their release goes
+    /// where their press went.
+    keys: HashMap<u32, Held<egui::Key>>,
+    buttons: HashMap<u32, Held<egui::PointerButton>>,
+}
+
+/// A key or button that is down.
+struct Held<T> {
+    /// Whether the application wasn't told about the press.
+    hidden: bool,
+    /// What egui was told is down, if anything.
+    egui: Option<T>,
+}
+
+impl Input {
+    fn push(&mut self, event: egui::Event) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    /// Tells egui every key and button it saw pressed is released.
+    fn release_egui(&mut self) {
+        let (pos, modifiers) = (self.pointer, self.modifiers);
+        let keys: Vec<_> = self
+            .keys
+            .values_mut()
+            .filter_map(|h| h.egui.take())
+            .collect();
+        for key in keys {
+            self.push(egui::Event::Key {
+                key,
+                pressed: false,
+                modifiers,
+            });
+        }
+        let buttons: Vec<_> = self
+            .buttons
+            .values_mut()
+            .filter_map(|h| h.egui.take())
+            .collect();
+        for button in buttons {
+            self.push(egui::Event::PointerButton {
+                pos,
+                button,
+                pressed: false,
+                modifiers,
+            });
+        }
+    }
+}
+
+/// Whether the application shouldn't see `code` being pressed or released. Releases
+/// follow their press, and a press repeated while held keeps the first one's fate.
+fn hides<T>(held: &HashMap<u32, Held<T>>, code: u32, pressed: bool, wants: bool) -> bool {
+    match held.get(&code) {
+        Some(held) => held.hidden,
+        None => pressed && wants,
+    }
+}
+
+/// Records the press or release of `code`, returns whether the application shouldn't see it.
+fn press<T>(
+    held: &mut HashMap<u32, Held<T>>,
+    code: u32,
+    pressed: bool,
+    wants: bool,
+    egui: Option<T>,
+) -> bool {
+    let hide = hides(held, code, pressed, wants);
+    if pressed {
+        let entry = held.entry(code).or_insert(Held {
+            hidden: hide,
+            egui: None,
+        });
+        if egui.is_some() {
+            entry.egui = egui;
+        }
+    } else {
+        held.remove(&code);
+    }
+    hide
+}
+
+/// Switches between passive and interactive mode.
+pub fn toggle_interactive() {
+    let mut input = INPUT.lock().unwrap();
+    input.interactive = !input.interactive;
+    if input.interactive {
+        let pointer = input.pointer;
+        input.push(egui::Event::PointerMoved(pointer));
+    } else {
+        // egui won't see the releases anymore
+        input.release_egui();
+        input.push(egui::Event::PointerGone);
+        input.wants_pointer = false;
+        input.wants_keyboard = false;
+    }
+}
+
+pub fn is_interactive() -> bool {
+    INPUT.lock().unwrap().interactive
+}
+
+/// What egui asked for in the last frame, events it wants are hidden from the application.
+pub fn set_wants(pointer: bool, keyboard: bool) {
+    let mut input = INPUT.lock().unwrap();
+    input.wants_pointer = input.interactive && pointer;
+    input.wants_keyboard = input.interactive && keyboard;
+}
+
+/// Restricts input to `window` and the other watched windows.
+pub fn watch_window(window: u64) {
+    INPUT.lock().unwrap().windows.insert(window);
+}
+
+pub fn is_watched(window: u64) -> bool {
+    let input = INPUT.lock().unwrap();
+    input.windows.is_empty() || input.windows.contains(&window)
+}
+
+/// Events since the last call, and the current modifiers.
+pub fn take() -> (Vec<egui::Event>, egui::Modifiers) {
+    let mut input = INPUT.lock().unwrap();
+    (std::mem::take(&mut input.events), input.modifiers)
+}
+
+pub fn set_modifiers(modifiers: egui::Modifiers) {
+    INPUT.lock().unwrap().modifiers = modifiers;
+}
+
+/// The application's window lost the keyboard, keys released from now on go to
+/// another window.
+pub fn keyboard_left() {
+    crate::hotkeys::release_all();
+    let mut input = INPUT.lock().unwrap();
+    input.release_egui();
+    input.keys.clear();
+}
+
+/// Whether the application shouldn't see the press or release of key `code`,
+/// without recording it.
+pub fn hides_key(code: u32, pressed: bool) -> bool {
+    let input = INPUT.lock().unwrap();
+    hides(&input.keys, code, pressed, input.wants_keyboard)
+}
+
+/// Same as `hides_key` for button `code`.
+pub fn hides_button(code: u32, pressed: bool) -> bool {
+    let input = INPUT.lock().unwrap();
+    hides(&input.buttons, code, pressed, input.wants_pointer)
+}
+
+/// Whether the application shouldn't see the pointer move.
+pub fn hides_motion() -> bool {
+    INPUT.lock().unwrap().wants_pointer
+}
+
+// the functions below return whether the application shouldn't see the event
+
+/// `x` and `y` are in pixels from the top left corner of the window.
+pub fn pointer_moved(x: f32, y: f32) -> bool {
+    let ppp = crate::frontends::pixels_per_point();
+    let pos = egui::pos2(x / ppp, y / ppp);
+    let mut input = INPUT.lock().unwrap();
+    // kept in passive mode too, for the cursor to start at the right place
+    input.pointer = pos;
+    if input.interactive {
+        input.push(egui::Event::PointerMoved(pos));
+    }
+    input.wants_pointer
+}
+
+pub fn pointer_gone() {
+    let mut input = INPUT.lock().unwrap();
+    if input.interactive {
+        input.push(egui::Event::PointerGone);
+    }
+}
+
+/// Button `code` of the windowing system changed state, `button` is what egui
+/// knows it as.
+pub fn pointer_button(code: u32, button: Option<egui::PointerButton>, pressed: bool) -> bool {
+    let mut input = INPUT.lock().unwrap();
+    let button = button.filter(|_| input.interactive);
+    let wants = input.wants_pointer;
+    let hide = press(
+        &mut input.buttons,
+        code,
+        pressed,
+        wants,
+        button.filter(|_| pressed),
+    );
+    if let Some(button) = button {
+        let event = egui::Event::PointerButton {
+            pos: input.pointer,
+            button,
+            pressed,
+            modifiers: input.modifiers,
+        };
+        input.push(event);
+    }
+    hide
+}
+
+/// `notches` of a wheel, positive when scrolling up or left.
+pub fn scroll(notches: egui::Vec2) -> bool {
+    let mut input = INPUT.lock().unwrap();
+    if !input.interactive {
+        return false;
+    }
+    input.push(egui::Event::Scroll(notches * SCROLL_STEP));
+    input.wants_pointer
+}
+
+/// Key `code`, an X/xkb keycode, with the `keysym` changed state.
+pub fn key(code: u32, keysym: u32, pressed: bool) -> bool {
+    crate::hotkeys::key(keysym, pressed);
+    let mut input = INPUT.lock().unwrap();
+    let key = keysym_to_key(keysym).filter(|_| input.interactive);
+    let wants = input.wants_keyboard;
+    let hide = press(
+        &mut input.keys,
+        code,
+        pressed,
+        wants,
+        key.filter(|_| pressed),
+    );
+    if !input.interactive {
+        return hide;
+    }
+    let modifiers = input.modifiers;
+    if let Some(key) = key {
+        input.push(egui::Event::Key {
+            key,
+            pressed,
+            modifiers,
+        });
+    }
+    if pressed && !modifiers.ctrl && !modifiers.alt {
+        if let Some(c) = keysym_to_char(keysym) {
+            input.push(egui::Event::Text(c.to_string()));
+        }
+    }
+    hide
+}
+
+fn keysym_to_key(keysym: u32) -> Option<egui::Key> {
+    use egui::Key;
+
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Num0,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+
+    Some(match keysym {
+        0xff51 => Key::ArrowLeft,
+        0xff52 => Key::ArrowUp,
+        0xff53 => Key::ArrowRight,
+        0xff54 => Key::ArrowDown,
+        0xff1b => Key::Escape,
+        0xff09 | 0xfe20 => Key::Tab,
+        0xff08 => Key::Backspace,
+        0xff0d | 0xff8d => Key::Enter,
+        0x20 => Key::Space,
+        0xff63 => Key::Insert,
+        0xffff => Key::Delete,
+        0xff50 => Key::Home,
+        0xff57 => Key::End,
+        0xff55 => Key::PageUp,
+        0xff56 => Key::PageDown,
+        0x30..=0x39 => DIGITS[(keysym - 0x30) as usize],
+        0x41..=0x5a => LETTERS[(keysym - 0x41) as usize],
+        0x61..=0x7a => LETTERS[(keysym - 0x61) as usize],
+        _ => return None,
+    })
+}
+
+fn keysym_to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        // Latin-1 keysyms are the characters themselves
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        0x0100_0000..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+        _ => None,
+    }
+}
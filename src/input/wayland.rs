@@ -0,0 +1,779 @@
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref WAYLAND: Wayland = Wayland::new();
+    static ref XKB: Option<Xkb> = Xkb::new();
+    /// Where the events of each wrapped proxy go.
+    static ref HANDLERS: Mutex<HashMap<usize, Handler>> = Mutex::new(HashMap::new());
+    static ref KEYMAP: Mutex<Option<Keymap>> = Mutex::new(None);
+    static ref AXIS_FRAMES: Mutex<HashMap<usize, AxisFrame>> = Mutex::new(HashMap::new());
+}
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+const XKB_KEYMAP_FORMAT_WL_KEYBOARD: u32 = 1;
+const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+
+/// Surface-local distance a wheel notch scrolls by.
+const AXIS_STEP: f32 = 10.;
+
+type Listener = *const Option<unsafe extern "C" fn()>;
+
+type Dispatcher = unsafe extern "C" fn(
+    *const c_void,
+    *mut c_void,
+    u32,
+    *const WlMessage,
+    *mut WlArgument,
+) -> c_int;
+
+// the following definitions come from `wayland-util.h`
+
+#[repr(C)]
+pub struct WlMessage {
+    name: *const c_char,
+    signature: *const c_char,
+    types: *const c_void,
+}
+
+#[repr(C)]
+struct WlInterface {
+    name: *const c_char,
+    version: c_int,
+    method_count: c_int,
+    methods: *const WlMessage,
+    event_count: c_int,
+    events: *const WlMessage,
+}
+
+/// An event argument, which member is set depends on the event's signature.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union WlArgument {
+    i: i32,
+    u: u32,
+    o: *mut c_void,
+}
+
+trait Argument {
+    fn argument(self) -> WlArgument;
+}
+
+impl Argument for i32 {
+    fn argument(self) -> WlArgument {
+        WlArgument { i: self }
+    }
+}
+
+impl Argument for u32 {
+    fn argument(self) -> WlArgument {
+        WlArgument { u: self }
+    }
+}
+
+impl Argument for *mut c_void {
+    fn argument(self) -> WlArgument {
+        WlArgument { o: self }
+    }
+}
+
+/// Where the events of a wrapped proxy go.
+#[derive(Clone, Copy)]
+enum Handler {
+    /// The application's listener.
+    Listener(usize),
+    /// The application's dispatcher, which wayland-rs and so winit use.
+    Dispatcher {
+        dispatcher: Dispatcher,
+        data: usize,
+        /// The event messages of the proxy's interface.
+        events: usize,
+    },
+}
+
+struct Wayland {
+    _lib: dlopen::raw::Library,
+    add_listener: unsafe extern "C" fn(*mut c_void, Listener, *mut c_void) -> c_int,
+    add_dispatcher:
+        unsafe extern "C" fn(*mut c_void, Dispatcher, *const c_void, *mut c_void) -> c_int,
+    destroy: unsafe extern "C" fn(*mut c_void),
+    get_class: unsafe extern "C" fn(*mut c_void) -> *const c_char,
+    pointer_events: usize,
+    keyboard_events: usize,
+}
+
+impl Wayland {
+    fn new() -> Self {
+        unsafe {
+            let lib = dlopen::raw::Library::open("libwayland-client.so.0").unwrap();
+            let add_listener = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_proxy_add_listener").unwrap())
+                .unwrap();
+            let add_dispatcher = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_proxy_add_dispatcher").unwrap())
+                .unwrap();
+            let destroy = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_proxy_destroy").unwrap())
+                .unwrap();
+            let get_class = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_proxy_get_class").unwrap())
+                .unwrap();
+            let pointer_interface: *const WlInterface = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_pointer_interface").unwrap())
+                .unwrap();
+            let keyboard_interface: *const WlInterface = lib
+                .symbol_cstr(&std::ffi::CString::new("wl_keyboard_interface").unwrap())
+                .unwrap();
+
+            Self {
+                _lib: lib,
+                add_listener,
+                add_dispatcher,
+                destroy,
+                get_class,
+                pointer_events: (*pointer_interface).events as usize,
+                keyboard_events: (*keyboard_interface).events as usize,
+            }
+        }
+    }
+}
+
+struct Xkb {
+    _lib: dlopen::raw::Library,
+    context: usize,
+    keymap_new_from_string: unsafe extern "C" fn(usize, *const c_char, c_int, c_int) -> usize,
+    keymap_unref: unsafe extern "C" fn(usize),
+    state_new: unsafe extern "C" fn(usize) -> usize,
+    state_unref: unsafe extern "C" fn(usize),
+    state_update_mask: unsafe extern "C" fn(usize, u32, u32, u32, u32, u32, u32) -> c_int,
+    state_key_get_one_sym: unsafe extern "C" fn(usize, u32) -> u32,
+    state_mod_name_is_active: unsafe extern "C" fn(usize, *const c_char, c_int) -> c_int,
+}
+
+impl Xkb {
+    fn new() -> Option<Self> {
+        unsafe {
+            let lib = match dlopen::raw::Library::open("libxkbcommon.so.0") {
+                Ok(lib) => lib,
+                Err(_) => {
+                    eprintln!("libxkbcommon not found, Wayland keys are ignored");
+                    return None;
+                }
+            };
+            let context_new: unsafe extern "C" fn(c_int) -> usize = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_context_new").unwrap())
+                .unwrap();
+            let keymap_new_from_string = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_keymap_new_from_string").unwrap())
+                .unwrap();
+            let keymap_unref = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_keymap_unref").unwrap())
+                .unwrap();
+            let state_new = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_state_new").unwrap())
+                .unwrap();
+            let state_unref = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_state_unref").unwrap())
+                .unwrap();
+            let state_update_mask = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_state_update_mask").unwrap())
+                .unwrap();
+            let state_key_get_one_sym = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_state_key_get_one_sym").unwrap())
+                .unwrap();
+            let state_mod_name_is_active = lib
+                .symbol_cstr(&std::ffi::CString::new("xkb_state_mod_name_is_active").unwrap())
+                .unwrap();
+
+            let context = context_new(0);
+            if context == 0 {
+                return None;
+            }
+
+            Some(Self {
+                _lib: lib,
+                context,
+                keymap_new_from_string,
+                keymap_unref,
+                state_new,
+                state_unref,
+                state_update_mask,
+                state_key_get_one_sym,
+                state_mod_name_is_active,
+            })
+        }
+    }
+}
+
+/// The keymap the compositor sent last, and the state of its modifiers.
+struct Keymap {
+    keymap: usize,
+    state: usize,
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        if let Some(xkb) = &*XKB {
+            unsafe {
+                (xkb.state_unref)(self.state);
+                (xkb.keymap_unref)(self.keymap);
+            }
+        }
+    }
+}
+
+/// Axis events of the pointer frame being received, which are all hidden or all
+/// shown along with its `axis` events.
+#[derive(Default)]
+struct AxisFrame {
+    /// Set by the frame's first `axis` event.
+    hidden: Option<bool>,
+    /// What the last frame with an `axis` event decided, for the frames that only
+    /// end a scroll.
+    last_hidden: bool,
+    /// Events received before the frame's first `axis` event.
+    held: Vec<AxisEvent>,
+}
+
+#[derive(Clone, Copy)]
+enum AxisEvent {
+    Source(u32),
+    Stop(u32, u32),
+    Discrete(u32, i32),
+    Value120(u32, i32),
+    RelativeDirection(u32, u32),
+}
+
+fn handler(proxy: *mut c_void) -> Option<Handler> {
+    HANDLERS.lock().unwrap().get(&(proxy as usize)).copied()
+}
+
+/// Calls the application's handler for the event being wrapped. `data` is only
+/// used by listeners, dispatchers have their own.
+macro_rules! forward {
+    ($opcode:expr, $data:ident, $proxy:ident $(, $arg:ident: $ty:ty)*) => {
+        match handler($proxy) {
+            Some(Handler::Listener(listener)) => {
+                // the compositor only sends events the listener was written for
+                if let Some(f) = *(listener as Listener).add($opcode) {
+                    let f: unsafe extern "C" fn(*mut c_void, *mut c_void $(, $ty)*) =
+                        std::mem::transmute(f);
+                    f($data, $proxy $(, $arg)*);
+                }
+            }
+            Some(Handler::Dispatcher { dispatcher, data, events }) => {
+                let args: &mut [WlArgument] = &mut [$(Argument::argument($arg)),*];
+                dispatcher(
+                    data as *const c_void,
+                    $proxy,
+                    $opcode,
+                    (events as *const WlMessage).add($opcode),
+                    args.as_mut_ptr(),
+                );
+            }
+            None => {}
+        }
+    };
+}
+
+fn fixed_to_f32(fixed: i32) -> f32 {
+    fixed as f32 / 256.
+}
+
+unsafe extern "C" fn pointer_enter(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    serial: u32,
+    surface: *mut c_void,
+    sx: i32,
+    sy: i32,
+) {
+    super::pointer_moved(fixed_to_f32(sx), fixed_to_f32(sy));
+    forward!(0, data, pointer, serial: u32, surface: *mut c_void, sx: i32, sy: i32);
+}
+
+unsafe extern "C" fn pointer_leave(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    serial: u32,
+    surface: *mut c_void,
+) {
+    super::pointer_gone();
+    forward!(1, data, pointer, serial: u32, surface: *mut c_void);
+}
+
+unsafe extern "C" fn pointer_motion(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    time: u32,
+    sx: i32,
+    sy: i32,
+) {
+    if !super::pointer_moved(fixed_to_f32(sx), fixed_to_f32(sy)) {
+        forward!(2, data, pointer, time: u32, sx: i32, sy: i32);
+    }
+}
+
+unsafe extern "C" fn pointer_button(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    serial: u32,
+    time: u32,
+    button: u32,
+    state: u32,
+) {
+    let egui_button = match button {
+        BTN_LEFT => Some(egui::PointerButton::Primary),
+        BTN_RIGHT => Some(egui::PointerButton::Secondary),
+        BTN_MIDDLE => Some(egui::PointerButton::Middle),
+        _ => None,
+    };
+    if !super::pointer_button(button, egui_button, state == 1) {
+        forward!(3, data, pointer, serial: u32, time: u32, button: u32, state: u32);
+    }
+}
+
+unsafe extern "C" fn pointer_axis(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    time: u32,
+    axis: u32,
+    value: i32,
+) {
+    // positive values scroll down or right, the other way around from egui
+    let notches = -fixed_to_f32(value) / AXIS_STEP;
+    let hide = match axis {
+        0 => super::scroll(egui::vec2(0., notches)),
+        1 => super::scroll(egui::vec2(notches, 0.)),
+        _ => false,
+    };
+    let (hide, held) = {
+        let mut frames = AXIS_FRAMES.lock().unwrap();
+        let frame = frames.entry(pointer as usize).or_default();
+        let hide = *frame.hidden.get_or_insert(hide);
+        (hide, std::mem::take(&mut frame.held))
+    };
+    if !hide {
+        for event in held {
+            forward_axis_event(data, pointer, event);
+        }
+        forward!(4, data, pointer, time: u32, axis: u32, value: i32);
+    }
+}
+
+unsafe extern "C" fn pointer_frame(data: *mut c_void, pointer: *mut c_void) {
+    let (hide, held) = {
+        let mut frames = AXIS_FRAMES.lock().unwrap();
+        let frame = frames.entry(pointer as usize).or_default();
+        let hide = frame.hidden.take().unwrap_or(frame.last_hidden);
+        frame.last_hidden = hide;
+        (hide, std::mem::take(&mut frame.held))
+    };
+    if !hide {
+        for event in held {
+            forward_axis_event(data, pointer, event);
+        }
+    }
+    forward!(5, data, pointer);
+}
+
+/// Forwards `event` once the frame's `axis` events decided whether it is hidden,
+/// holds it back until then.
+unsafe fn axis_event(data: *mut c_void, pointer: *mut c_void, event: AxisEvent) {
+    let hide = {
+        let mut frames = AXIS_FRAMES.lock().unwrap();
+        let frame = frames.entry(pointer as usize).or_default();
+        match frame.hidden {
+            Some(hide) => hide,
+            None => {
+                frame.held.push(event);
+                return;
+            }
+        }
+    };
+    if !hide {
+        forward_axis_event(data, pointer, event);
+    }
+}
+
+unsafe fn forward_axis_event(data: *mut c_void, pointer: *mut c_void, event: AxisEvent) {
+    match event {
+        AxisEvent::Source(source) => {
+            forward!(6, data, pointer, source: u32);
+        }
+        AxisEvent::Stop(time, axis) => {
+            forward!(7, data, pointer, time: u32, axis: u32);
+        }
+        AxisEvent::Discrete(axis, discrete) => {
+            forward!(8, data, pointer, axis: u32, discrete: i32);
+        }
+        AxisEvent::Value120(axis, value120) => {
+            forward!(9, data, pointer, axis: u32, value120: i32);
+        }
+        AxisEvent::RelativeDirection(axis, direction) => {
+            forward!(10, data, pointer, axis: u32, direction: u32);
+        }
+    }
+}
+
+unsafe extern "C" fn pointer_axis_source(data: *mut c_void, pointer: *mut c_void, source: u32) {
+    axis_event(data, pointer, AxisEvent::Source(source));
+}
+
+unsafe extern "C" fn pointer_axis_stop(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    time: u32,
+    axis: u32,
+) {
+    axis_event(data, pointer, AxisEvent::Stop(time, axis));
+}
+
+unsafe extern "C" fn pointer_axis_discrete(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    axis: u32,
+    discrete: i32,
+) {
+    axis_event(data, pointer, AxisEvent::Discrete(axis, discrete));
+}
+
+unsafe extern "C" fn pointer_axis_value120(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    axis: u32,
+    value120: i32,
+) {
+    axis_event(data, pointer, AxisEvent::Value120(axis, value120));
+}
+
+unsafe extern "C" fn pointer_axis_relative_direction(
+    data: *mut c_void,
+    pointer: *mut c_void,
+    axis: u32,
+    direction: u32,
+) {
+    axis_event(data, pointer, AxisEvent::RelativeDirection(axis, direction));
+}
+
+unsafe extern "C" fn keyboard_keymap(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    format: u32,
+    fd: c_int,
+    size: u32,
+) {
+    // the application owns the fd and closes it, the map is only borrowed here
+    if let (Some(xkb), XKB_KEYMAP_FORMAT_WL_KEYBOARD) = (&*XKB, format) {
+        let map = libc::mmap(
+            std::ptr::null_mut(),
+            size as usize,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            fd,
+            0,
+        );
+        if map != libc::MAP_FAILED {
+            let keymap = (xkb.keymap_new_from_string)(
+                xkb.context,
+                map as *const c_char,
+                XKB_KEYMAP_FORMAT_TEXT_V1,
+                0,
+            );
+            libc::munmap(map, size as usize);
+            *KEYMAP.lock().unwrap() = if keymap != 0 {
+                Some(Keymap {
+                    keymap,
+                    state: (xkb.state_new)(keymap),
+                })
+            } else {
+                None
+            };
+        }
+    }
+    forward!(0, data, keyboard, format: u32, fd: c_int, size: u32);
+}
+
+unsafe extern "C" fn keyboard_enter(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    serial: u32,
+    surface: *mut c_void,
+    keys: *mut c_void,
+) {
+    forward!(1, data, keyboard, serial: u32, surface: *mut c_void, keys: *mut c_void);
+}
+
+unsafe extern "C" fn keyboard_leave(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    serial: u32,
+    surface: *mut c_void,
+) {
+    // keys released from now on go to another client
+    super::keyboard_left();
+    forward!(2, data, keyboard, serial: u32, surface: *mut c_void);
+}
+
+unsafe extern "C" fn keyboard_key(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    serial: u32,
+    time: u32,
+    key: u32,
+    state: u32,
+) {
+    let hide = match (&*XKB, &*KEYMAP.lock().unwrap()) {
+        (Some(xkb), Some(keymap)) => {
+            // xkb keycodes are evdev ones shifted by 8
+            let keysym = (xkb.state_key_get_one_sym)(keymap.state, key + 8);
+            super::key(key + 8, keysym, state == 1)
+        }
+        _ => false,
+    };
+    if !hide {
+        forward!(3, data, keyboard, serial: u32, time: u32, key: u32, state: u32);
+    }
+}
+
+unsafe extern "C" fn keyboard_modifiers(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    serial: u32,
+    depressed: u32,
+    latched: u32,
+    locked: u32,
+    group: u32,
+) {
+    if let (Some(xkb), Some(keymap)) = (&*XKB, &*KEYMAP.lock().unwrap()) {
+        (xkb.state_update_mask)(keymap.state, depressed, latched, locked, 0, 0, group);
+        let active = |name: &[u8]| {
+            (xkb.state_mod_name_is_active)(
+                keymap.state,
+                name.as_ptr() as *const c_char,
+                XKB_STATE_MODS_EFFECTIVE,
+            ) > 0
+        };
+        let ctrl = active(b"Control\0");
+        super::set_modifiers(egui::Modifiers {
+            alt: active(b"Mod1\0"),
+            ctrl,
+            shift: active(b"Shift\0"),
+            mac_cmd: false,
+            command: ctrl,
+        });
+    }
+    forward!(
+        4,
+        data,
+        keyboard,
+        serial: u32,
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32
+    );
+}
+
+unsafe extern "C" fn keyboard_repeat_info(
+    data: *mut c_void,
+    keyboard: *mut c_void,
+    rate: i32,
+    delay: i32,
+) {
+    forward!(5, data, keyboard, rate: i32, delay: i32);
+}
+
+/// Listener arrays in the layout libwayland expects, with every event known so far.
+struct Wrapper([*const c_void; 11]);
+
+// only holds function pointers
+unsafe impl Sync for Wrapper {}
+
+static POINTER_LISTENER: Wrapper = Wrapper([
+    pointer_enter as *const c_void,
+    pointer_leave as *const c_void,
+    pointer_motion as *const c_void,
+    pointer_button as *const c_void,
+    pointer_axis as *const c_void,
+    pointer_frame as *const c_void,
+    pointer_axis_source as *const c_void,
+    pointer_axis_stop as *const c_void,
+    pointer_axis_discrete as *const c_void,
+    pointer_axis_value120 as *const c_void,
+    pointer_axis_relative_direction as *const c_void,
+]);
+
+static KEYBOARD_LISTENER: Wrapper = Wrapper([
+    keyboard_keymap as *const c_void,
+    keyboard_enter as *const c_void,
+    keyboard_leave as *const c_void,
+    keyboard_key as *const c_void,
+    keyboard_modifiers as *const c_void,
+    keyboard_repeat_info as *const c_void,
+    std::ptr::null(),
+    std::ptr::null(),
+    std::ptr::null(),
+    std::ptr::null(),
+    std::ptr::null(),
+]);
+
+/// Hands the events of a pointer or keyboard with a dispatcher to the functions of
+/// our listeners, `wrapper` tells which.
+unsafe extern "C" fn dispatch(
+    wrapper: *const c_void,
+    proxy: *mut c_void,
+    opcode: u32,
+    message: *const WlMessage,
+    args: *mut WlArgument,
+) -> c_int {
+    let arg = |i: usize| *args.add(i);
+    let data = std::ptr::null_mut();
+    let pointer = std::ptr::eq(wrapper as *const Wrapper, &POINTER_LISTENER);
+    match (pointer, opcode) {
+        (true, 0) => pointer_enter(data, proxy, arg(0).u, arg(1).o, arg(2).i, arg(3).i),
+        (true, 1) => pointer_leave(data, proxy, arg(0).u, arg(1).o),
+        (true, 2) => pointer_motion(data, proxy, arg(0).u, arg(1).i, arg(2).i),
+        (true, 3) => pointer_button(data, proxy, arg(0).u, arg(1).u, arg(2).u, arg(3).u),
+        (true, 4) => pointer_axis(data, proxy, arg(0).u, arg(1).u, arg(2).i),
+        (true, 5) => pointer_frame(data, proxy),
+        (true, 6) => pointer_axis_source(data, proxy, arg(0).u),
+        (true, 7) => pointer_axis_stop(data, proxy, arg(0).u, arg(1).u),
+        (true, 8) => pointer_axis_discrete(data, proxy, arg(0).u, arg(1).i),
+        (true, 9) => pointer_axis_value120(data, proxy, arg(0).u, arg(1).i),
+        (true, 10) => pointer_axis_relative_direction(data, proxy, arg(0).u, arg(1).u),
+        (false, 0) => keyboard_keymap(data, proxy, arg(0).u, arg(1).i, arg(2).u),
+        (false, 1) => keyboard_enter(data, proxy, arg(0).u, arg(1).o, arg(2).o),
+        (false, 2) => keyboard_leave(data, proxy, arg(0).u, arg(1).o),
+        (false, 3) => keyboard_key(data, proxy, arg(0).u, arg(1).u, arg(2).u, arg(3).u),
+        (false, 4) => keyboard_modifiers(
+            data,
+            proxy,
+            arg(0).u,
+            arg(1).u,
+            arg(2).u,
+            arg(3).u,
+            arg(4).u,
+        ),
+        (false, 5) => keyboard_repeat_info(data, proxy, arg(0).i, arg(1).i),
+        // events added after this was written go straight to the application
+        _ => {
+            if let Some(Handler::Dispatcher {
+                dispatcher, data, ..
+            }) = handler(proxy)
+            {
+                return dispatcher(data as *const c_void, proxy, opcode, message, args);
+            }
+        }
+    }
+    0
+}
+
+/// Our listener for pointers and keyboards, and the event messages of their interface.
+fn wrapper(proxy: *mut c_void) -> Option<(&'static Wrapper, usize)> {
+    match unsafe { CStr::from_ptr((WAYLAND.get_class)(proxy)) }.to_bytes() {
+        b"wl_pointer" => Some((&POINTER_LISTENER, WAYLAND.pointer_events)),
+        b"wl_keyboard" => Some((&KEYBOARD_LISTENER, WAYLAND.keyboard_events)),
+        _ => None,
+    }
+}
+
+fn wrap(proxy: *mut c_void, handler: Handler) {
+    HANDLERS.lock().unwrap().insert(proxy as usize, handler);
+    // a proxy destroyed without `wl_proxy_destroy` may have had the same address
+    AXIS_FRAMES.lock().unwrap().remove(&(proxy as usize));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_wl_proxy_add_listener(
+    proxy: *mut c_void,
+    implementation: Listener,
+    data: *mut c_void,
+) -> c_int {
+    wl_proxy_add_listener(proxy, implementation, data)
+}
+
+/// Puts our listener in front of the application's one for pointers and keyboards.
+///
+/// `data` is left untouched so `wl_proxy_get_user_data` still returns what the
+/// application expects, the original listener is looked up by proxy instead.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_add_listener(
+    proxy: *mut c_void,
+    implementation: Listener,
+    data: *mut c_void,
+) -> c_int {
+    let wrapper = match wrapper(proxy) {
+        Some((wrapper, _)) => wrapper,
+        None => return (WAYLAND.add_listener)(proxy, implementation, data),
+    };
+
+    let res = (WAYLAND.add_listener)(proxy, wrapper.0.as_ptr() as Listener, data);
+    // a proxy can only get one listener, on failure the application's one isn't used either
+    if res == 0 {
+        wrap(proxy, Handler::Listener(implementation as usize));
+    }
+    res
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_wl_proxy_add_dispatcher(
+    proxy: *mut c_void,
+    dispatcher: Dispatcher,
+    dispatcher_data: *const c_void,
+    data: *mut c_void,
+) -> c_int {
+    wl_proxy_add_dispatcher(proxy, dispatcher, dispatcher_data, data)
+}
+
+/// Same as `wl_proxy_add_listener` for the proxies whose events go through a
+/// dispatcher, the events are translated to the calls of a listener.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_add_dispatcher(
+    proxy: *mut c_void,
+    dispatcher: Dispatcher,
+    dispatcher_data: *const c_void,
+    data: *mut c_void,
+) -> c_int {
+    let (wrapper, events) = match wrapper(proxy) {
+        Some(wrapper) => wrapper,
+        None => return (WAYLAND.add_dispatcher)(proxy, dispatcher, dispatcher_data, data),
+    };
+
+    let res = (WAYLAND.add_dispatcher)(
+        proxy,
+        dispatch,
+        wrapper as *const Wrapper as *const c_void,
+        data,
+    );
+    if res == 0 {
+        wrap(
+            proxy,
+            Handler::Dispatcher {
+                dispatcher,
+                data: dispatcher_data as usize,
+                events,
+            },
+        );
+    }
+    res
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_wl_proxy_destroy(proxy: *mut c_void) {
+    wl_proxy_destroy(proxy)
+}
+
+/// Forgets `proxy` before its address can be reused.
+#[no_mangle]
+pub unsafe extern "C" fn wl_proxy_destroy(proxy: *mut c_void) {
+    HANDLERS.lock().unwrap().remove(&(proxy as usize));
+    AXIS_FRAMES.lock().unwrap().remove(&(proxy as usize));
+    (WAYLAND.destroy)(proxy)
+}
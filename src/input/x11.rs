@@ -0,0 +1,482 @@
+use std::ffi::c_void;
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref XLIB: Xlib = Xlib::new();
+    static ref XCB: Xcb = Xcb::new();
+    /// xcb connections of the displays Xlib reads events from. Xlib reads them
+    /// through xcb itself, so they are only looked at when Xlib hands them out.
+    static ref XLIB_CONNECTIONS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+}
+
+const KEY_PRESS: c_int = 2;
+const KEY_RELEASE: c_int = 3;
+const BUTTON_PRESS: c_int = 4;
+const BUTTON_RELEASE: c_int = 5;
+const MOTION_NOTIFY: c_int = 6;
+const ENTER_NOTIFY: c_int = 7;
+const LEAVE_NOTIFY: c_int = 8;
+const FOCUS_OUT: c_int = 10;
+
+const QUEUED_ALREADY: c_int = 0;
+
+const XCB_OWNS_EVENT_QUEUE: c_int = 1;
+
+const SHIFT_MASK: c_uint = 1 << 0;
+const CONTROL_MASK: c_uint = 1 << 2;
+const MOD1_MASK: c_uint = 1 << 3;
+
+#[repr(C)]
+pub struct XEvent {
+    pad: [c_long; 24],
+}
+
+/// Layout shared by key, button, motion and crossing events, up to `y_root`.
+#[allow(dead_code)]
+#[repr(C)]
+struct XInputEvent {
+    type_: c_int,
+    serial: c_ulong,
+    send_event: c_int,
+    display: *mut c_void,
+    window: c_ulong,
+    root: c_ulong,
+    subwindow: c_ulong,
+    time: c_ulong,
+    x: c_int,
+    y: c_int,
+    x_root: c_int,
+    y_root: c_int,
+    state: c_uint,
+    detail: c_uint,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+pub struct XcbGenericEvent {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    pad: [u32; 7],
+    full_sequence: u32,
+}
+
+/// Layout shared by key, button, motion and crossing events.
+#[allow(dead_code)]
+#[repr(C)]
+struct XcbInputEvent {
+    response_type: u8,
+    detail: u8,
+    sequence: u16,
+    time: u32,
+    root: u32,
+    event: u32,
+    child: u32,
+    root_x: i16,
+    root_y: i16,
+    event_x: i16,
+    event_y: i16,
+    state: u16,
+}
+
+type Predicate = unsafe extern "C" fn(*mut c_void, *mut XEvent, *mut c_char) -> c_int;
+
+struct Xlib {
+    _lib: dlopen::raw::Library,
+    _xcb_lib: dlopen::raw::Library,
+    open_display: unsafe extern "C" fn(*const c_char) -> *mut c_void,
+    close_display: unsafe extern "C" fn(*mut c_void) -> c_int,
+    next_event: unsafe extern "C" fn(*mut c_void, *mut XEvent) -> c_int,
+    peek_event: unsafe extern "C" fn(*mut c_void, *mut XEvent) -> c_int,
+    check_mask_event: unsafe extern "C" fn(*mut c_void, c_long, *mut XEvent) -> c_int,
+    check_if_event: unsafe extern "C" fn(*mut c_void, *mut XEvent, Predicate, *mut c_char) -> c_int,
+    pending: unsafe extern "C" fn(*mut c_void) -> c_int,
+    events_queued: unsafe extern "C" fn(*mut c_void, c_int) -> c_int,
+    get_xcb_connection: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+    set_event_queue_owner: unsafe extern "C" fn(*mut c_void, c_int),
+}
+
+impl Xlib {
+    fn new() -> Self {
+        unsafe {
+            let lib = dlopen::raw::Library::open("libX11.so.6").unwrap();
+            let xcb_lib = dlopen::raw::Library::open("libX11-xcb.so.1").unwrap();
+            let open_display = lib
+                .symbol_cstr(&std::ffi::CString::new("XOpenDisplay").unwrap())
+                .unwrap();
+            let close_display = lib
+                .symbol_cstr(&std::ffi::CString::new("XCloseDisplay").unwrap())
+                .unwrap();
+            let next_event = lib
+                .symbol_cstr(&std::ffi::CString::new("XNextEvent").unwrap())
+                .unwrap();
+            let peek_event = lib
+                .symbol_cstr(&std::ffi::CString::new("XPeekEvent").unwrap())
+                .unwrap();
+            let check_mask_event = lib
+                .symbol_cstr(&std::ffi::CString::new("XCheckMaskEvent").unwrap())
+                .unwrap();
+            let check_if_event = lib
+                .symbol_cstr(&std::ffi::CString::new("XCheckIfEvent").unwrap())
+                .unwrap();
+            let pending = lib
+                .symbol_cstr(&std::ffi::CString::new("XPending").unwrap())
+                .unwrap();
+            let events_queued = lib
+                .symbol_cstr(&std::ffi::CString::new("XEventsQueued").unwrap())
+                .unwrap();
+            let get_xcb_connection = xcb_lib
+                .symbol_cstr(&std::ffi::CString::new("XGetXCBConnection").unwrap())
+                .unwrap();
+            let set_event_queue_owner = xcb_lib
+                .symbol_cstr(&std::ffi::CString::new("XSetEventQueueOwner").unwrap())
+                .unwrap();
+
+            Self {
+                _lib: lib,
+                _xcb_lib: xcb_lib,
+                open_display,
+                close_display,
+                next_event,
+                peek_event,
+                check_mask_event,
+                check_if_event,
+                pending,
+                events_queued,
+                get_xcb_connection,
+                set_event_queue_owner,
+            }
+        }
+    }
+}
+
+struct Xcb {
+    _lib: dlopen::raw::Library,
+    wait_for_event: unsafe extern "C" fn(*mut c_void) -> *mut XcbGenericEvent,
+    poll_for_event: unsafe extern "C" fn(*mut c_void) -> *mut XcbGenericEvent,
+    poll_for_queued_event: unsafe extern "C" fn(*mut c_void) -> *mut XcbGenericEvent,
+}
+
+impl Xcb {
+    fn new() -> Self {
+        unsafe {
+            let lib = dlopen::raw::Library::open("libxcb.so.1").unwrap();
+            let wait_for_event = lib
+                .symbol_cstr(&std::ffi::CString::new("xcb_wait_for_event").unwrap())
+                .unwrap();
+            let poll_for_event = lib
+                .symbol_cstr(&std::ffi::CString::new("xcb_poll_for_event").unwrap())
+                .unwrap();
+            let poll_for_queued_event = lib
+                .symbol_cstr(&std::ffi::CString::new("xcb_poll_for_queued_event").unwrap())
+                .unwrap();
+
+            Self {
+                _lib: lib,
+                wait_for_event,
+                poll_for_event,
+                poll_for_queued_event,
+            }
+        }
+    }
+}
+
+/// Whether `translate` would hide the event, without feeding it to egui.
+fn hides(type_: c_int, window: u64, detail: c_uint) -> bool {
+    if !super::is_watched(window) {
+        return false;
+    }
+    match type_ {
+        KEY_PRESS | KEY_RELEASE => super::hides_key(detail, type_ == KEY_PRESS),
+        BUTTON_PRESS | BUTTON_RELEASE => super::hides_button(detail, type_ == BUTTON_PRESS),
+        MOTION_NOTIFY => super::hides_motion(),
+        _ => false,
+    }
+}
+
+/// Feeds one event to egui, with coordinates relative to `window`. Returns whether
+/// the application shouldn't see it.
+fn translate(type_: c_int, window: u64, x: i32, y: i32, state: c_uint, detail: c_uint) -> bool {
+    if type_ == FOCUS_OUT && super::is_watched(window) {
+        super::keyboard_left();
+    }
+    if !(KEY_PRESS..=LEAVE_NOTIFY).contains(&type_) || !super::is_watched(window) {
+        return false;
+    }
+
+    // Xlib's crossing events have other fields where the others have `state`
+    if type_ != ENTER_NOTIFY && type_ != LEAVE_NOTIFY {
+        super::set_modifiers(egui::Modifiers {
+            alt: state & MOD1_MASK != 0,
+            ctrl: state & CONTROL_MASK != 0,
+            shift: state & SHIFT_MASK != 0,
+            mac_cmd: false,
+            command: state & CONTROL_MASK != 0,
+        });
+    }
+
+    match type_ {
+        KEY_PRESS | KEY_RELEASE => {
+            let keysym = crate::keyboard::keysym(detail as u8, state & SHIFT_MASK != 0);
+            super::key(detail, keysym as u32, type_ == KEY_PRESS)
+        }
+        BUTTON_PRESS | BUTTON_RELEASE => {
+            let pressed = type_ == BUTTON_PRESS;
+            super::pointer_moved(x as f32, y as f32);
+            let button = match detail {
+                1 => Some(egui::PointerButton::Primary),
+                2 => Some(egui::PointerButton::Middle),
+                3 => Some(egui::PointerButton::Secondary),
+                _ => None,
+            };
+            // wheels are buttons that are released right away
+            if pressed {
+                match detail {
+                    4 => super::scroll(egui::vec2(0., 1.)),
+                    5 => super::scroll(egui::vec2(0., -1.)),
+                    6 => super::scroll(egui::vec2(1., 0.)),
+                    7 => super::scroll(egui::vec2(-1., 0.)),
+                    _ => false,
+                };
+            }
+            super::pointer_button(detail, button, pressed)
+        }
+        MOTION_NOTIFY => super::pointer_moved(x as f32, y as f32),
+        ENTER_NOTIFY => {
+            super::pointer_moved(x as f32, y as f32);
+            false
+        }
+        LEAVE_NOTIFY => {
+            super::pointer_gone();
+            false
+        }
+        _ => false,
+    }
+}
+
+unsafe fn translate_xlib(event: *const XEvent) -> bool {
+    let event = &*(event as *const XInputEvent);
+    translate(
+        event.type_,
+        event.window,
+        event.x,
+        event.y,
+        event.state,
+        event.detail,
+    )
+}
+
+unsafe extern "C" fn hides_xlib(_dpy: *mut c_void, event: *mut XEvent, _arg: *mut c_char) -> c_int {
+    let event = &*(event as *const XInputEvent);
+    hides(event.type_, event.window, event.detail) as c_int
+}
+
+/// Takes the events the application shouldn't see out of the queue, so what
+/// `XPending` and `XEventsQueued` count is handed out by `XNextEvent` without blocking.
+unsafe fn drop_hidden(dpy: *mut c_void) {
+    let mut event = XEvent { pad: [0; 24] };
+    while (XLIB.check_if_event)(dpy, &mut event, hides_xlib, std::ptr::null_mut()) != 0 {
+        translate_xlib(&event);
+    }
+}
+
+unsafe fn translate_xcb(c: *mut c_void, event: *const XcbGenericEvent) -> bool {
+    if event.is_null() || XLIB_CONNECTIONS.lock().unwrap().contains(&(c as usize)) {
+        return false;
+    }
+    let event = &*(event as *const XcbInputEvent);
+    let type_ = (event.response_type & 0x7f) as c_int;
+    // focus events have their window where input events have their time
+    let window = if type_ == FOCUS_OUT {
+        event.time
+    } else {
+        event.event
+    };
+    translate(
+        type_,
+        window as u64,
+        event.event_x as i32,
+        event.event_y as i32,
+        event.state as c_uint,
+        event.detail as c_uint,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_open_display(name: *const c_char) -> *mut c_void {
+    XOpenDisplay(name)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_close_display(dpy: *mut c_void) -> c_int {
+    XCloseDisplay(dpy)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_set_event_queue_owner(dpy: *mut c_void, owner: c_int) {
+    XSetEventQueueOwner(dpy, owner)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_next_event(dpy: *mut c_void, event: *mut XEvent) -> c_int {
+    XNextEvent(dpy, event)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_peek_event(dpy: *mut c_void, event: *mut XEvent) -> c_int {
+    XPeekEvent(dpy, event)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_check_mask_event(
+    dpy: *mut c_void,
+    mask: c_long,
+    event: *mut XEvent,
+) -> c_int {
+    XCheckMaskEvent(dpy, mask, event)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_pending(dpy: *mut c_void) -> c_int {
+    XPending(dpy)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_x_events_queued(dpy: *mut c_void, mode: c_int) -> c_int {
+    XEventsQueued(dpy, mode)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_xcb_wait_for_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    xcb_wait_for_event(c)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_xcb_poll_for_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    xcb_poll_for_event(c)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_xcb_poll_for_queued_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    xcb_poll_for_queued_event(c)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XOpenDisplay(name: *const c_char) -> *mut c_void {
+    let dpy = (XLIB.open_display)(name);
+    if !dpy.is_null() {
+        let c = (XLIB.get_xcb_connection)(dpy);
+        XLIB_CONNECTIONS.lock().unwrap().push(c as usize);
+    }
+    dpy
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCloseDisplay(dpy: *mut c_void) -> c_int {
+    let c = (XLIB.get_xcb_connection)(dpy) as usize;
+    XLIB_CONNECTIONS.lock().unwrap().retain(|&other| other != c);
+    (XLIB.close_display)(dpy)
+}
+
+/// Once xcb owns the event queue the application reads events through it.
+#[no_mangle]
+pub unsafe extern "C" fn XSetEventQueueOwner(dpy: *mut c_void, owner: c_int) {
+    let c = (XLIB.get_xcb_connection)(dpy) as usize;
+    let mut connections = XLIB_CONNECTIONS.lock().unwrap();
+    connections.retain(|&other| other != c);
+    if owner != XCB_OWNS_EVENT_QUEUE {
+        connections.push(c);
+    }
+    drop(connections);
+    (XLIB.set_event_queue_owner)(dpy, owner)
+}
+
+/// Hidden events are skipped, those `XPending` counted were already taken out of
+/// the queue so applications that only call it when events are pending don't block.
+#[no_mangle]
+pub unsafe extern "C" fn XNextEvent(dpy: *mut c_void, event: *mut XEvent) -> c_int {
+    loop {
+        let res = (XLIB.next_event)(dpy, event);
+        if !translate_xlib(event) {
+            return res;
+        }
+    }
+}
+
+/// Peeked events stay in the queue, hidden ones are taken out and translated
+/// until one the application may see is at its head.
+#[no_mangle]
+pub unsafe extern "C" fn XPeekEvent(dpy: *mut c_void, event: *mut XEvent) -> c_int {
+    loop {
+        let res = (XLIB.peek_event)(dpy, event);
+        if hides_xlib(dpy, event, std::ptr::null_mut()) == 0 {
+            return res;
+        }
+        (XLIB.next_event)(dpy, event);
+        translate_xlib(event);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XCheckMaskEvent(
+    dpy: *mut c_void,
+    mask: c_long,
+    event: *mut XEvent,
+) -> c_int {
+    loop {
+        let found = (XLIB.check_mask_event)(dpy, mask, event);
+        if found == 0 || !translate_xlib(event) {
+            return found;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XPending(dpy: *mut c_void) -> c_int {
+    // reads what the server sent, for the hidden events among it to be dropped too
+    let pending = (XLIB.pending)(dpy);
+    if pending == 0 {
+        return 0;
+    }
+    drop_hidden(dpy);
+    (XLIB.events_queued)(dpy, QUEUED_ALREADY)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn XEventsQueued(dpy: *mut c_void, mode: c_int) -> c_int {
+    if (XLIB.events_queued)(dpy, mode) == 0 {
+        return 0;
+    }
+    drop_hidden(dpy);
+    (XLIB.events_queued)(dpy, QUEUED_ALREADY)
+}
+
+/// Calls `next` until it returns an event the application may see, or none.
+unsafe fn next_shown(
+    next: unsafe extern "C" fn(*mut c_void) -> *mut XcbGenericEvent,
+    c: *mut c_void,
+) -> *mut XcbGenericEvent {
+    loop {
+        let event = next(c);
+        if !translate_xcb(c, event) {
+            return event;
+        }
+        libc::free(event as *mut c_void);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xcb_wait_for_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    next_shown(XCB.wait_for_event, c)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xcb_poll_for_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    next_shown(XCB.poll_for_event, c)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn xcb_poll_for_queued_event(c: *mut c_void) -> *mut XcbGenericEvent {
+    next_shown(XCB.poll_for_queued_event, c)
+}
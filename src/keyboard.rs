@@ -0,0 +1,53 @@
+use std::ffi::c_void;
+use std::os::raw::{c_char, c_int, c_ulong};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref KEYBOARD: Mutex<Option<Keyboard>> = Mutex::new(Keyboard::new());
+}
+
+/// Our own X connection, to look keycodes up without touching the application's one.
+pub struct Keyboard {
+    _lib: dlopen::raw::Library,
+    display: *mut c_void,
+    keycode_to_keysym: unsafe extern "C" fn(*mut c_void, u8, c_int, c_int) -> c_ulong,
+}
+
+// the display is only ever used behind `KEYBOARD`'s lock
+unsafe impl Send for Keyboard {}
+
+impl Keyboard {
+    fn new() -> Option<Self> {
+        unsafe {
+            let lib = dlopen::raw::Library::open("libX11.so.6").ok()?;
+            let open_display: unsafe extern "C" fn(*const c_char) -> *mut c_void = lib
+                .symbol_cstr(&std::ffi::CString::new("XOpenDisplay").unwrap())
+                .unwrap();
+            let keycode_to_keysym = lib
+                .symbol_cstr(&std::ffi::CString::new("XkbKeycodeToKeysym").unwrap())
+                .unwrap();
+
+            let display = open_display(std::ptr::null());
+            if display.is_null() {
+                eprintln!("could not open the X display, X keys are ignored");
+                return None;
+            }
+
+            Some(Self {
+                _lib: lib,
+                display,
+                keycode_to_keysym,
+            })
+        }
+    }
+}
+
+/// Keysym of `keycode` in the first group, shifted or not.
+pub fn keysym(keycode: u8, shift: bool) -> c_ulong {
+    match &*KEYBOARD.lock().unwrap() {
+        Some(keyboard) => unsafe {
+            (keyboard.keycode_to_keysym)(keyboard.display, keycode, 0, shift as c_int)
+        },
+        None => 0,
+    }
+}
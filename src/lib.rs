@@ -8,10 +8,74 @@ lazy_static! {
 }
 
 mod backends;
+mod benchmark;
+mod config;
+mod control;
+mod cursor;
+mod frame_clock;
 pub mod frontends;
+mod hotkeys;
+mod hud;
+mod input;
+mod keyboard;
+mod limiter;
+mod logging;
+mod metrics;
+mod plugins;
+mod screenshot;
+mod shm_stats;
+pub mod widgets;
 
-fn ui_fn(ctx: &egui::Context) {
-    egui::Window::new("TEST")
-        .resize(|r| r.auto_sized())
-        .show(ctx, |ui| ui.label("other"));
+fn ui_fn(ctx: &egui::Context, clock: &frame_clock::FrameClock) {
+    config::apply_style(ctx);
+    plugins::load();
+    let interactive = input::is_interactive();
+    if hud::HUD.lock().unwrap().is_visible() {
+        widgets::show(
+            ctx,
+            &widgets::FrameInfo {
+                frame_times: clock.frame_times(),
+                interactive,
+            },
+        );
+    }
+    config::show_notice(ctx);
+    if interactive {
+        cursor::show(ctx);
+    }
+    input::set_wants(ctx.wants_pointer_input(), ctx.wants_keyboard_input());
+}
+
+/// Applies a configuration read since the last frame and the actions of the hotkeys
+/// pressed and the commands received meanwhile, then hands the presented frame to everything that records them.
+fn on_frame(frame_time: std::time::Duration, info: &logging::RendererInfo) {
+    config::poll();
+    for action in hotkeys::take() {
+        run_action(action, info);
+    }
+    frame_clock::RECENT.lock().unwrap().push(frame_time);
+    control::poll(info);
+    shm_stats::publish(frame_time, info);
+    logging::LOGGER.lock().unwrap().frame(frame_time, info);
+    benchmark::BENCHMARK.lock().unwrap().frame(frame_time, info);
+}
+
+fn run_action(action: hotkeys::Action, info: &logging::RendererInfo) {
+    use hotkeys::Action;
+
+    match action {
+        Action::ToggleOverlay => hud::HUD.lock().unwrap().toggle(),
+        Action::ToggleLogging => logging::LOGGER.lock().unwrap().toggle(info),
+        Action::ToggleInteractive => input::toggle_interactive(),
+        Action::StopBenchmark => benchmark::BENCHMARK.lock().unwrap().stop(),
+        Action::Screenshot if info.frontend == "vulkan" => {
+            eprintln!("screenshots are only taken with OpenGL")
+        }
+        Action::Screenshot => screenshot::request(),
+        Action::CyclePosition => hud::HUD
+            .lock()
+            .unwrap()
+            .cycle_position(&config::CONFIG.lock().unwrap()),
+        Action::ReloadConfig => config::reload(),
+    }
 }
@@ -0,0 +1,127 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, CONFIG};
+
+lazy_static! {
+    pub static ref LIMITER: Mutex<FrameLimiter> =
+        Mutex::new(FrameLimiter::new(&CONFIG.lock().unwrap()));
+}
+
+/// Sleeping is only precise to a scheduler tick or so, the end of the wait is spun instead.
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+/// Checks an FPS limit, 0 turns the limiter off.
+pub fn check_fps(fps: f64) -> Result<f64, String> {
+    // below 1 the interval can't be represented at all once small enough
+    if fps == 0. || (fps.is_finite() && fps >= 1.) {
+        Ok(fps)
+    } else {
+        Err(format!("expected 0 or at least 1 FPS, got {}", fps))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LimitMethod {
+    /// Wait before handing the frame to the real swap, for even frame pacing.
+    Early,
+    /// Wait after the real swap returned, for lower latency.
+    Late,
+}
+
+impl std::str::FromStr for LimitMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "early" => Ok(LimitMethod::Early),
+            "late" => Ok(LimitMethod::Late),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct FrameLimiter {
+    interval: Option<Duration>,
+    method: LimitMethod,
+    deadline: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(config: &Config) -> Self {
+        let mut limiter = FrameLimiter {
+            interval: None,
+            method: LimitMethod::Late,
+            deadline: None,
+        };
+        limiter.configure(config);
+        limiter
+    }
+
+    /// Takes the limit and method from the configuration, unless `OVERLIB_FPS_LIMIT`
+    /// (0 disables the limiter) or `OVERLIB_FPS_LIMIT_METHOD` (`early` or `late`) are set.
+    pub fn configure(&mut self, config: &Config) {
+        self.set_fps(config.fps_limit.unwrap_or(0.));
+        self.method = config.fps_limit_method.unwrap_or(LimitMethod::Late);
+        if let Ok(fps) = std::env::var("OVERLIB_FPS_LIMIT") {
+            match fps.trim().parse() {
+                Ok(value) if check_fps(value).is_ok() => self.set_fps(value),
+                _ => eprintln!("invalid OVERLIB_FPS_LIMIT: {}", fps),
+            }
+        }
+        if let Ok(method) = std::env::var("OVERLIB_FPS_LIMIT_METHOD") {
+            match method.trim().parse() {
+                Ok(method) => self.method = method,
+                Err(_) => eprintln!("invalid OVERLIB_FPS_LIMIT_METHOD: {}", method),
+            }
+        }
+    }
+
+    /// Limits that don't pass `check_fps` turn the limiter off.
+    pub fn set_fps(&mut self, fps: f64) {
+        self.interval = match check_fps(fps) {
+            Ok(fps) if fps > 0. => Some(Duration::from_secs_f64(1. / fps)),
+            _ => None,
+        };
+        self.deadline = None;
+    }
+
+    /// Blocks until the next frame is due.
+    fn wait(&mut self) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let deadline = match self.deadline {
+            // a frame that was late already restarts the schedule instead of bursting to catch up
+            Some(deadline) if deadline > now => deadline,
+            Some(_) | None => {
+                self.deadline = Some(now + interval);
+                return;
+            }
+        };
+
+        if let Some(sleep) = (deadline - now).checked_sub(SPIN_MARGIN) {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        self.deadline = Some(deadline + interval);
+    }
+}
+
+/// Calls `present`, waiting before or after it so frames don't come faster than the limit.
+pub fn limit<R>(present: impl FnOnce() -> R) -> R {
+    let method = LIMITER.lock().unwrap().method;
+    if method == LimitMethod::Early {
+        LIMITER.lock().unwrap().wait();
+    }
+    let result = present();
+    if method == LimitMethod::Late {
+        LIMITER.lock().unwrap().wait();
+    }
+    result
+}
@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    pub static ref LOGGER: Mutex<Logger> = Mutex::new(Logger::from_env());
+}
+
+/// Columns of MangoHud's CSV logs, which overlib only fills the frame timing of,
+/// the others are left empty.
+const COLUMNS: &str = "fps,frametime,cpu_load,gpu_load,cpu_temp,gpu_temp,gpu_core_clock,\
+gpu_mem_clock,gpu_vram_used,gpu_power,ram_used,swap_used,process_rss,elapsed";
+
+/// How often buffered rows are written out, in case the application exits while logging.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the recorded frames are rendered with.
+pub struct RendererInfo<'a> {
+    pub frontend: &'a str,
+    pub gpu: &'a str,
+    pub driver: &'a str,
+    pub width: u32,
+    pub height: u32,
+}
+
+struct Session {
+    file: BufWriter<File>,
+    start: Instant,
+    last_flush: Instant,
+}
+
+/// Writes one CSV row per frame while a session runs, toggled by the `toggle_logging` hotkey.
+pub struct Logger {
+    output_folder: PathBuf,
+    /// Sessions stop by themselves after this long.
+    duration: Option<Duration>,
+    /// When a session starts without the hotkey, counted from the first frame.
+    autostart: Option<Duration>,
+    first_frame: Option<Instant>,
+    session: Option<Session>,
+}
+
+/// Where logs and reports go, `OVERLIB_OUTPUT_FOLDER` or the home directory.
+pub fn output_folder() -> PathBuf {
+    std::env::var_os("OVERLIB_OUTPUT_FOLDER")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+}
+
+pub fn env_secs(name: &str) -> Option<Duration> {
+    let value = std::env::var(name).ok()?;
+    match value.trim().parse::<f64>() {
+        Ok(secs) if secs >= 0. => Some(Duration::from_secs_f64(secs)),
+        _ => {
+            eprintln!("invalid {}: {}", name, value);
+            None
+        }
+    }
+}
+
+impl Logger {
+    /// Reads `OVERLIB_LOG_DURATION` and `OVERLIB_AUTOSTART_LOG`, both in seconds.
+    pub fn from_env() -> Self {
+        Logger {
+            output_folder: output_folder(),
+            duration: env_secs("OVERLIB_LOG_DURATION").filter(|d| !d.is_zero()),
+            autostart: env_secs("OVERLIB_AUTOSTART_LOG"),
+            first_frame: None,
+            session: None,
+        }
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.session.is_some()
+    }
+
+    pub fn start(&mut self, info: &RendererInfo) {
+        let name = format!("{}_{}", executable_name(), local_timestamp());
+        let (path, mut file) = match create_new(&self.output_folder, &name, "csv") {
+            Ok((path, file)) => (path, BufWriter::new(file)),
+            Err(e) => {
+                eprintln!(
+                    "could not create {}: {}",
+                    self.output_folder.join(name).display(),
+                    e
+                );
+                return;
+            }
+        };
+        let header = writeln!(file, "os,cpu,gpu,ram,kernel,driver,cpuscheduler")
+            .and_then(|_| {
+                writeln!(
+                    file,
+                    "{},{},{},{},{},{},{}",
+                    os_name(),
+                    cpu_name(),
+                    info.gpu.replace(',', ""),
+                    ram_total(),
+                    kernel_version(),
+                    info.driver.replace(',', ""),
+                    cpu_scheduler()
+                )
+            })
+            .and_then(|_| writeln!(file, "{}", COLUMNS));
+        if let Err(e) = header {
+            eprintln!("could not write to {}: {}", path.display(), e);
+            return;
+        }
+
+        eprintln!("logging frames to {}", path.display());
+        let now = Instant::now();
+        self.session = Some(Session {
+            file,
+            start: now,
+            last_flush: now,
+        });
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut session) = self.session.take() {
+            if let Err(e) = session.file.flush() {
+                eprintln!("could not write the frame log: {}", e);
+            }
+            eprintln!("frame logging stopped");
+        }
+    }
+
+    pub fn toggle(&mut self, info: &RendererInfo) {
+        if self.is_logging() {
+            self.stop();
+        } else {
+            self.start(info);
+        }
+    }
+
+    /// Records a frame that took `frame_time`, starting the log when autostart is due.
+    pub fn frame(&mut self, frame_time: Duration, info: &RendererInfo) {
+        let now = Instant::now();
+        let first_frame = *self.first_frame.get_or_insert(now);
+
+        if let Some(autostart) = self.autostart {
+            if now - first_frame >= autostart {
+                self.autostart = None;
+                self.start(info);
+            }
+        }
+
+        let session = match &mut self.session {
+            Some(session) => session,
+            None => return,
+        };
+        let elapsed = now - session.start;
+        let frametime_ms = frame_time.as_secs_f64() * 1000.;
+        let fps = if frametime_ms > 0. {
+            1000. / frametime_ms
+        } else {
+            0.
+        };
+        let row = writeln!(
+            session.file,
+            "{:.3},{:.3},,,,,,,,,,,,{}",
+            fps,
+            frametime_ms,
+            elapsed.as_nanos()
+        );
+        if let Err(e) = row {
+            eprintln!("could not write the frame log: {}", e);
+            self.session = None;
+            return;
+        }
+        if now - session.last_flush >= FLUSH_INTERVAL {
+            session.last_flush = now;
+            session.file.flush().ok();
+        }
+
+        if matches!(self.duration, Some(duration) if elapsed >= duration) {
+            self.stop();
+        }
+    }
+}
+
+/// Creates `<name>.<extension>` in `folder`, or `<name>_2.<extension>` and so on when
+/// it exists, timestamps only change every second.
+fn create_new(folder: &Path, name: &str, extension: &str) -> std::io::Result<(PathBuf, File)> {
+    let mut n = 1;
+    loop {
+        let path = match n {
+            1 => folder.join(format!("{}.{}", name, extension)),
+            _ => folder.join(format!("{}_{}.{}", name, n, extension)),
+        };
+        match File::options().write(true).create_new(true).open(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && n < 100 => n += 1,
+            file => return file.map(|file| (path, file)),
+        }
+    }
+}
+
+pub fn executable_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn local_timestamp() -> String {
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        format!(
+            "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+}
+
+/// Value of the first `key<separator>value` line of a file, commas are removed to keep the CSV valid.
+fn read_field(path: &str, key: &str, separator: char) -> String {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let (k, v) = line.split_once(separator)?;
+                (k.trim() == key).then(|| v.trim().trim_matches('"').replace(',', ""))
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn os_name() -> String {
+    read_field("/etc/os-release", "PRETTY_NAME", '=')
+}
+
+fn cpu_name() -> String {
+    read_field("/proc/cpuinfo", "model name", ':')
+}
+
+fn ram_total() -> String {
+    let kb: f64 = read_field("/proc/meminfo", "MemTotal", ':')
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .unwrap_or(0.);
+    format!("{:.0} GB", kb / 1024. / 1024.)
+}
+
+fn kernel_version() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn cpu_scheduler() -> String {
+    std::fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
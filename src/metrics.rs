@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use egui::plot::{Line, Plot, Value, Values};
+
+use crate::config::CONFIG;
+use crate::hud::HUD;
+use crate::widgets::{FrameInfo, OverlayWidget};
+
+lazy_static! {
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
+
+/// Values kept per metric for its graph.
+const HISTORY_LEN: usize = 200;
+
+/// Metrics and texts past this many are dropped, in case names are built from changing values.
+const MAX_KEYS: usize = 64;
+
+const MAX_EVENTS: usize = 8;
+
+/// How long marked events stay listed.
+const EVENT_DISPLAY: Duration = Duration::from_secs(10);
+
+/// What the application pushed through the `overlib_*` functions below.
+#[derive(Default)]
+struct Metrics {
+    values: BTreeMap<String, VecDeque<f64>>,
+    texts: BTreeMap<String, String>,
+    events: VecDeque<(Instant, String)>,
+}
+
+/// A string from the application, `None` when null.
+unsafe fn string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+}
+
+/// Adds `value` to the graph of the metric `name`, NaN and infinities are dropped.
+pub fn push_metric(name: &str, value: f64) {
+    // the plot's bounds would follow them
+    if !value.is_finite() {
+        return;
+    }
+    let mut metrics = METRICS.lock().unwrap();
+    if !metrics.values.contains_key(name) {
+        if metrics.values.len() == MAX_KEYS {
+            return;
+        }
+        metrics.values.insert(name.to_string(), VecDeque::new());
+    }
+    let values = metrics.values.get_mut(name).unwrap();
+    if values.len() == HISTORY_LEN {
+        values.pop_front();
+    }
+    values.push_back(value);
+}
+
+/// Shows `text` next to `key`, replacing what it had. `None` removes it.
+pub fn push_text(key: String, text: Option<String>) {
+    let mut metrics = METRICS.lock().unwrap();
+    match text {
+        Some(text) if metrics.texts.len() < MAX_KEYS || metrics.texts.contains_key(&key) => {
+            metrics.texts.insert(key, text);
+        }
+        Some(_) => {}
+        None => {
+            metrics.texts.remove(&key);
+        }
+    }
+}
+
+/// Lists `label` with the time it happened for a while.
+pub fn mark_event(label: String) {
+    let mut metrics = METRICS.lock().unwrap();
+    if metrics.events.len() == MAX_EVENTS {
+        metrics.events.pop_front();
+    }
+    metrics.events.push_back((Instant::now(), label));
+}
+
+// applications find these with `dlsym(RTLD_DEFAULT, ...)`, so they keep working
+// when overlib isn't loaded, see `include/overlib.h`
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_push_metric(name: *const c_char, value: f64) {
+    if name.is_null() {
+        return;
+    }
+    // only allocates for new metrics
+    push_metric(&CStr::from_ptr(name).to_string_lossy(), value);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_push_text(key: *const c_char, text: *const c_char) {
+    if let Some(key) = string(key) {
+        push_text(key, string(text));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn overlib_mark_event(label: *const c_char) {
+    if let Some(label) = string(label) {
+        mark_event(label);
+    }
+}
+
+/// Everything the application pushed, hidden until it pushes something. Drawn in
+/// the corner below or above the HUD.
+pub struct Widget;
+
+impl OverlayWidget for Widget {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn ui(&mut self, ctx: &egui::Context, _frame: &FrameInfo) {
+        let (align, offset) = HUD.lock().unwrap().other_corner(&CONFIG.lock().unwrap());
+        let mut metrics = METRICS.lock().unwrap();
+        while matches!(metrics.events.front(), Some((at, _)) if at.elapsed() >= EVENT_DISPLAY) {
+            metrics.events.pop_front();
+        }
+        if metrics.values.is_empty() && metrics.texts.is_empty() && metrics.events.is_empty() {
+            return;
+        }
+
+        egui::Window::new("metrics")
+            .resizable(false)
+            .anchor(align, offset)
+            .show(ctx, |ui| {
+                egui::Grid::new("overlib_metric_texts").show(ui, |ui| {
+                    for (key, text) in &metrics.texts {
+                        ui.label(key);
+                        ui.label(text);
+                        ui.end_row();
+                    }
+                });
+
+                for (name, values) in &metrics.values {
+                    ui.label(format!("{}: {}", name, values.back().unwrap()));
+                    let points = values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| Value::new(i as f64, *v));
+                    Plot::new(("overlib_metric", name))
+                        .height(40.)
+                        .width(200.)
+                        .include_x(0.)
+                        .include_x(HISTORY_LEN as f64)
+                        .show_axes([false, true])
+                        .show_x(false)
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .allow_scroll(false)
+                        .allow_boxed_zoom(false)
+                        .show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(Values::from_values_iter(points)))
+                        });
+                }
+
+                for (at, label) in metrics.events.iter().rev() {
+                    ui.label(format!(
+                        "{} ({:.1} s ago)",
+                        label,
+                        at.elapsed().as_secs_f32()
+                    ));
+                }
+            });
+    }
+
+    fn order(&self) -> i32 {
+        20
+    }
+}
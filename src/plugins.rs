@@ -0,0 +1,374 @@
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::{c_char, c_float, c_int};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Once};
+
+use crate::widgets::{FrameInfo, OverlayWidget};
+
+lazy_static! {
+    /// Every plugin that was initialized, to shut them down when the process exits.
+    static ref PLUGINS: Mutex<Vec<Arc<Mutex<Plugin>>>> = Mutex::new(vec![]);
+}
+
+static LOAD: Once = Once::new();
+
+/// Version of the structures below, see `include/overlib_plugin.h`. Plugins built
+/// for another version are not loaded.
+pub const ABI_VERSION: u32 = 1;
+
+/// Opaque to plugins, only handed back to the `OverlibApi` functions.
+#[repr(transparent)]
+pub struct OverlibUi(egui::Ui);
+
+/// What a plugin's `overlib_plugin` function returns.
+#[repr(C)]
+pub struct OverlibPlugin {
+    abi_version: u32,
+    name: *const c_char,
+    /// Returns the data given to the other functions, null when the plugin can't run.
+    init: Option<unsafe extern "C" fn(api: *const OverlibApi) -> *mut c_void>,
+    /// Draws into a window of its own, a non-zero return disables the plugin.
+    draw: Option<
+        unsafe extern "C" fn(
+            data: *mut c_void,
+            ui: *mut OverlibUi,
+            frame: *const OverlibFrame,
+        ) -> c_int,
+    >,
+    shutdown: Option<unsafe extern "C" fn(data: *mut c_void)>,
+}
+
+#[repr(C)]
+pub struct OverlibFrame {
+    frame_time_ms: f64,
+    fps: f64,
+    interactive: c_int,
+}
+
+/// Immediate-mode functions a plugin draws with, each maps to the egui widget of the same name.
+#[repr(C)]
+pub struct OverlibApi {
+    abi_version: u32,
+    label: unsafe extern "C" fn(*mut OverlibUi, *const c_char),
+    /// The color is `0xRRGGBBAA`.
+    colored_label: unsafe extern "C" fn(*mut OverlibUi, u32, *const c_char),
+    heading: unsafe extern "C" fn(*mut OverlibUi, *const c_char),
+    /// Returns 1 when clicked.
+    button: unsafe extern "C" fn(*mut OverlibUi, *const c_char) -> c_int,
+    /// Returns 1 when `value` changed.
+    checkbox: unsafe extern "C" fn(*mut OverlibUi, *const c_char, *mut c_int) -> c_int,
+    /// Returns 1 when `value` changed.
+    slider: unsafe extern "C" fn(
+        *mut OverlibUi,
+        *const c_char,
+        *mut c_float,
+        c_float,
+        c_float,
+    ) -> c_int,
+    progress_bar: unsafe extern "C" fn(*mut OverlibUi, c_float, *const c_char),
+    separator: unsafe extern "C" fn(*mut OverlibUi),
+    /// Calls `contents` with a ui laying its widgets out left to right.
+    horizontal: unsafe extern "C" fn(
+        *mut OverlibUi,
+        Option<unsafe extern "C" fn(*mut c_void, *mut OverlibUi)>,
+        *mut c_void,
+    ),
+}
+
+static API: OverlibApi = OverlibApi {
+    abi_version: ABI_VERSION,
+    label: api_label,
+    colored_label: api_colored_label,
+    heading: api_heading,
+    button: api_button,
+    checkbox: api_checkbox,
+    slider: api_slider,
+    progress_bar: api_progress_bar,
+    separator: api_separator,
+    horizontal: api_horizontal,
+};
+
+/// Text from a plugin, a null pointer is an empty string.
+unsafe fn text(text: *const c_char) -> String {
+    if text.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(text).to_string_lossy().into_owned()
+}
+
+// plugins can hand anything to these, and a panic can't unwind into them
+
+/// Runs the body of an API function, `default` if it panics.
+fn catch<R>(default: R, f: impl FnOnce() -> R) -> R {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+unsafe extern "C" fn api_label(ui: *mut OverlibUi, label: *const c_char) {
+    if let Some(ui) = ui.as_mut() {
+        catch((), || {
+            ui.0.label(text(label));
+        })
+    }
+}
+
+unsafe extern "C" fn api_colored_label(ui: *mut OverlibUi, rgba: u32, label: *const c_char) {
+    if let Some(ui) = ui.as_mut() {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        catch((), || {
+            ui.0.colored_label(
+                egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+                text(label),
+            );
+        })
+    }
+}
+
+unsafe extern "C" fn api_heading(ui: *mut OverlibUi, label: *const c_char) {
+    if let Some(ui) = ui.as_mut() {
+        catch((), || {
+            ui.0.heading(text(label));
+        })
+    }
+}
+
+unsafe extern "C" fn api_button(ui: *mut OverlibUi, label: *const c_char) -> c_int {
+    match ui.as_mut() {
+        Some(ui) => catch(0, || ui.0.button(text(label)).clicked() as c_int),
+        None => 0,
+    }
+}
+
+unsafe extern "C" fn api_checkbox(
+    ui: *mut OverlibUi,
+    label: *const c_char,
+    value: *mut c_int,
+) -> c_int {
+    let (ui, value) = match (ui.as_mut(), value.as_mut()) {
+        (Some(ui), Some(value)) => (ui, value),
+        _ => return 0,
+    };
+    let mut checked = *value != 0;
+    let changed = catch(false, || ui.0.checkbox(&mut checked, text(label)).changed());
+    *value = checked as c_int;
+    changed as c_int
+}
+
+unsafe extern "C" fn api_slider(
+    ui: *mut OverlibUi,
+    label: *const c_char,
+    value: *mut c_float,
+    min: c_float,
+    max: c_float,
+) -> c_int {
+    let (ui, value) = match (ui.as_mut(), value.as_mut()) {
+        (Some(ui), Some(value)) => (ui, value),
+        _ => return 0,
+    };
+    catch(0, || {
+        ui.0.add(egui::Slider::new(value, min..=max).text(text(label)))
+            .changed() as c_int
+    })
+}
+
+unsafe extern "C" fn api_progress_bar(ui: *mut OverlibUi, progress: c_float, label: *const c_char) {
+    if let Some(ui) = ui.as_mut() {
+        let mut bar = egui::ProgressBar::new(progress);
+        if !label.is_null() {
+            bar = bar.text(text(label));
+        }
+        catch((), || {
+            ui.0.add(bar);
+        })
+    }
+}
+
+unsafe extern "C" fn api_separator(ui: *mut OverlibUi) {
+    if let Some(ui) = ui.as_mut() {
+        catch((), || {
+            ui.0.separator();
+        })
+    }
+}
+
+unsafe extern "C" fn api_horizontal(
+    ui: *mut OverlibUi,
+    contents: Option<unsafe extern "C" fn(*mut c_void, *mut OverlibUi)>,
+    data: *mut c_void,
+) {
+    if let (Some(ui), Some(contents)) = (ui.as_mut(), contents) {
+        catch((), || {
+            ui.0.horizontal(|ui| contents(data, ui_handle(ui)));
+        })
+    }
+}
+
+/// `OverlibUi` is a transparent wrapper, so the pointer can be cast.
+fn ui_handle(ui: &mut egui::Ui) -> *mut OverlibUi {
+    ui as *mut egui::Ui as *mut OverlibUi
+}
+
+struct Plugin {
+    name: String,
+    /// Null once the plugin is shut down or disabled.
+    data: *mut c_void,
+    draw: unsafe extern "C" fn(*mut c_void, *mut OverlibUi, *const OverlibFrame) -> c_int,
+    shutdown: Option<unsafe extern "C" fn(*mut c_void)>,
+    _lib: dlopen::raw::Library,
+}
+
+// plugins are only called behind their lock
+unsafe impl Send for Plugin {}
+
+impl Plugin {
+    /// Loads and initializes the plugin at `path`, or says why it couldn't.
+    unsafe fn load(path: &std::path::Path) -> Result<Self, String> {
+        let lib = dlopen::raw::Library::open(path).map_err(|e| e.to_string())?;
+        let entry: unsafe extern "C" fn() -> *const OverlibPlugin = lib
+            .symbol_cstr(&CString::new("overlib_plugin").unwrap())
+            .map_err(|_| "no overlib_plugin function".to_string())?;
+        let plugin = entry()
+            .as_ref()
+            .ok_or_else(|| "overlib_plugin returned null".to_string())?;
+        if plugin.abi_version != ABI_VERSION {
+            return Err(format!(
+                "built for ABI version {}, overlib has version {}",
+                plugin.abi_version, ABI_VERSION
+            ));
+        }
+        let name = text(plugin.name);
+        if name.is_empty() {
+            return Err("the plugin has no name".to_string());
+        }
+        if crate::widgets::BUILTIN_NAMES.contains(&name.as_str()) {
+            return Err(format!("{} is the name of a built-in widget", name));
+        }
+        let (init, draw) = match (plugin.init, plugin.draw) {
+            (Some(init), Some(draw)) => (init, draw),
+            _ => return Err("init or draw is missing".to_string()),
+        };
+
+        let data = init(&API);
+        if data.is_null() {
+            return Err(format!("{} failed to initialize", name));
+        }
+        Ok(Plugin {
+            name,
+            data,
+            draw,
+            shutdown: plugin.shutdown,
+            _lib: lib,
+        })
+    }
+
+    fn shutdown(&mut self) {
+        if self.data.is_null() {
+            return;
+        }
+        if let Some(shutdown) = self.shutdown {
+            unsafe { shutdown(self.data) };
+        }
+        self.data = std::ptr::null_mut();
+    }
+}
+
+struct PluginWidget {
+    name: String,
+    plugin: Arc<Mutex<Plugin>>,
+}
+
+impl OverlayWidget for PluginWidget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn ui(&mut self, ctx: &egui::Context, frame: &FrameInfo) {
+        let mut plugin = self.plugin.lock().unwrap();
+        if plugin.data.is_null() {
+            return;
+        }
+
+        let frame_time = frame.frame_times.back().copied().unwrap_or_default();
+        let frame_time_ms = frame_time.as_secs_f64() * 1000.;
+        let frame = OverlibFrame {
+            frame_time_ms,
+            fps: if frame_time_ms > 0. {
+                1000. / frame_time_ms
+            } else {
+                0.
+            },
+            interactive: frame.interactive as c_int,
+        };
+        let mut failed = false;
+        egui::Window::new(&self.name)
+            .resizable(false)
+            .show(ctx, |ui| {
+                failed = unsafe { (plugin.draw)(plugin.data, ui_handle(ui), &frame) } != 0;
+            });
+        if failed {
+            eprintln!("plugin {} failed, disabling it", plugin.name);
+            plugin.shutdown();
+        }
+    }
+}
+
+/// `OVERLIB_PLUGIN_DIR`, or `overlib/plugins` in the XDG data directory.
+fn plugin_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("OVERLIB_PLUGIN_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    Some(data_home.join("overlib").join("plugins"))
+}
+
+extern "C" fn shutdown_all() {
+    if let Ok(plugins) = PLUGINS.lock() {
+        for plugin in plugins.iter() {
+            if let Ok(mut plugin) = plugin.lock() {
+                plugin.shutdown();
+            }
+        }
+    }
+}
+
+/// Loads the `.so` files of the plugin directory as widgets, the first time it is called.
+pub fn load() {
+    LOAD.call_once(|| {
+        let dir = match plugin_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                eprintln!("could not read {}: {}", dir.display(), e);
+                return;
+            }
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "so"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match unsafe { Plugin::load(&path) } {
+                Ok(plugin) => {
+                    eprintln!("loaded plugin {} from {}", plugin.name, path.display());
+                    let name = plugin.name.clone();
+                    let plugin = Arc::new(Mutex::new(plugin));
+                    PLUGINS.lock().unwrap().push(plugin.clone());
+                    crate::widgets::register(Box::new(PluginWidget { name, plugin }));
+                }
+                Err(e) => eprintln!("skipping plugin {}: {}", path.display(), e),
+            }
+        }
+        if !PLUGINS.lock().unwrap().is_empty() {
+            unsafe { libc::atexit(shutdown_all) };
+        }
+    });
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::logging;
+
+/// Set by the `screenshot` hotkey, taken by the next frame drawn with OpenGL.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+pub fn take_request() -> bool {
+    REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Writes RGB `pixels`, bottom row first, as a PPM image next to the frame logs.
+pub fn save(width: u32, height: u32, pixels: &[u8]) {
+    let path = logging::output_folder().join(format!(
+        "{}_{}.ppm",
+        logging::executable_name(),
+        logging::local_timestamp()
+    ));
+    let row = width as usize * 3;
+    let write = std::fs::File::create(&path).and_then(|file| {
+        let mut file = std::io::BufWriter::new(file);
+        write!(file, "P6\n{} {}\n255\n", width, height)?;
+        for line in pixels.chunks_exact(row).rev() {
+            file.write_all(line)?;
+        }
+        file.flush()
+    });
+    match write {
+        Ok(()) => eprintln!("screenshot written to {}", path.display()),
+        Err(e) => eprintln!("could not write {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,154 @@
+use std::ffi::CString;
+use std::ptr::{addr_of_mut, write_volatile, NonNull};
+use std::sync::atomic::{fence, AtomicU32, Ordering};
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use crate::config::CONFIG;
+use crate::frame_clock;
+use crate::logging::RendererInfo;
+
+lazy_static! {
+    static ref PUBLISHER: Mutex<Publisher> = Mutex::new(Publisher::default());
+    /// `/dev/shm/overlib-<pid>`.
+    static ref NAME: CString = CString::new(format!("/overlib-{}", std::process::id())).unwrap();
+}
+
+static OPEN: Once = Once::new();
+
+/// The process that created the segment, its forks neither write nor remove it.
+static OWNER: AtomicU32 = AtomicU32::new(0);
+
+/// `OVLB`, written once the rest of the header is, see `include/overlib_stats.h`.
+const MAGIC: u32 = u32::from_le_bytes(*b"OVLB");
+
+/// Version of `Stats`, fields are only ever added at its end.
+const VERSION: u32 = 1;
+
+/// The segment's layout, the same as `OverlibStats` in `include/overlib_stats.h`.
+#[repr(C)]
+struct Stats {
+    magic: u32,
+    version: u32,
+    size: u32,
+    pid: u32,
+    /// Odd while the fields below are written. Readers copy them, then retry if it
+    /// changed meanwhile.
+    seq: AtomicU32,
+    /// Which of the optional fields are measured, none so far.
+    flags: u32,
+    frame_count: u64,
+    frame_time_ns: u64,
+    fps: f64,
+    /// Unavailable and always 0: a layer only sees presents, and the time between
+    /// them on the GPU's timeline includes however long it waited for the
+    /// application, which isn't the GPU time.
+    gpu_time_ns: u64,
+    width: u32,
+    height: u32,
+    /// Nul terminated.
+    frontend: [u8; 16],
+}
+
+#[derive(Default)]
+struct Publisher {
+    segment: Option<NonNull<Stats>>,
+    frame_count: u64,
+}
+
+// the mapping stays valid until the process exits
+unsafe impl Send for Publisher {}
+
+extern "C" fn unlink() {
+    if OWNER.load(Ordering::Relaxed) == std::process::id() {
+        unsafe { libc::shm_unlink(NAME.as_ptr()) };
+    }
+}
+
+/// Creates and maps the segment, leaving the fields to the first `publish`.
+unsafe fn open() -> Result<NonNull<Stats>, std::io::Error> {
+    let size = std::mem::size_of::<Stats>();
+    // truncated in case a process that had the same pid left one
+    let fd = libc::shm_open(
+        NAME.as_ptr(),
+        libc::O_CREAT | libc::O_TRUNC | libc::O_RDWR | libc::O_CLOEXEC,
+        0o600,
+    );
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    OWNER.store(std::process::id(), Ordering::Relaxed);
+    let mapped = if libc::ftruncate(fd, size as libc::off_t) == 0 {
+        libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        )
+    } else {
+        libc::MAP_FAILED
+    };
+    let error = std::io::Error::last_os_error();
+    libc::close(fd);
+    if mapped == libc::MAP_FAILED {
+        unlink();
+        return Err(error);
+    }
+    libc::atexit(unlink);
+
+    let stats = mapped as *mut Stats;
+    write_volatile(addr_of_mut!((*stats).version), VERSION);
+    write_volatile(addr_of_mut!((*stats).size), size as u32);
+    write_volatile(addr_of_mut!((*stats).pid), std::process::id());
+    fence(Ordering::Release);
+    write_volatile(addr_of_mut!((*stats).magic), MAGIC);
+    Ok(NonNull::new_unchecked(stats))
+}
+
+/// Creates the segment once `shm_stats` is set, then writes the frame to it.
+/// Called once per frame.
+pub fn publish(frame_time: Duration, info: &RendererInfo) {
+    let mut publisher = PUBLISHER.lock().unwrap();
+    publisher.frame_count += 1;
+    if !OPEN.is_completed() && CONFIG.lock().unwrap().shm_stats {
+        OPEN.call_once(|| match unsafe { open() } {
+            Ok(segment) => {
+                eprintln!(
+                    "publishing frame statistics to /dev/shm{}",
+                    NAME.to_string_lossy()
+                );
+                publisher.segment = Some(segment);
+            }
+            Err(e) => eprintln!("could not create the statistics segment: {}", e),
+        });
+    }
+    let stats = match publisher.segment {
+        Some(segment) if OWNER.load(Ordering::Relaxed) == std::process::id() => segment.as_ptr(),
+        _ => return,
+    };
+
+    let mut frontend = [0u8; 16];
+    let name = &info.frontend.as_bytes()[..info.frontend.len().min(frontend.len() - 1)];
+    frontend[..name.len()].copy_from_slice(name);
+    let fps = frame_clock::RECENT.lock().unwrap().fps();
+
+    unsafe {
+        let seq = &(*stats).seq;
+        let start = seq.load(Ordering::Relaxed);
+        seq.store(start.wrapping_add(1), Ordering::Relaxed);
+        // keeps the writes below after the odd sequence number
+        fence(Ordering::Release);
+        write_volatile(addr_of_mut!((*stats).frame_count), publisher.frame_count);
+        write_volatile(
+            addr_of_mut!((*stats).frame_time_ns),
+            frame_time.as_nanos() as u64,
+        );
+        write_volatile(addr_of_mut!((*stats).fps), fps);
+        write_volatile(addr_of_mut!((*stats).width), info.width);
+        write_volatile(addr_of_mut!((*stats).height), info.height);
+        write_volatile(addr_of_mut!((*stats).frontend), frontend);
+        seq.store(start.wrapping_add(2), Ordering::Release);
+    }
+}
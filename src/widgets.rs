@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{Config, CONFIG};
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Vec<Entry>> = Mutex::new(builtins());
+}
+
+/// What widgets get to know about the frame being drawn.
+pub struct FrameInfo<'a> {
+    /// Durations of the last frames of the drawable, oldest first.
+    pub frame_times: &'a VecDeque<Duration>,
+    /// Whether the overlay takes input, see the `toggle_interactive` hotkey.
+    pub interactive: bool,
+}
+
+/// Something drawn in the overlay every frame.
+///
+/// The `widgets` setting lists the names of the widgets to draw in the order to
+/// draw them in, e.g. `widgets=hud+benchmark`. Without it every widget enabled
+/// by default is drawn by increasing `order`.
+pub trait OverlayWidget: Send {
+    /// Unique name, a widget registered with the name of another replaces it.
+    fn name(&self) -> &str;
+
+    fn ui(&mut self, ctx: &egui::Context, frame: &FrameInfo);
+
+    /// Widgets drawn later go on top.
+    fn order(&self) -> i32 {
+        0
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+}
+
+struct Entry {
+    widget: Box<dyn OverlayWidget>,
+    enabled: bool,
+    order: i32,
+}
+
+impl Entry {
+    fn new(widget: Box<dyn OverlayWidget>, config: &Config) -> Self {
+        let mut entry = Entry {
+            widget,
+            enabled: false,
+            order: 0,
+        };
+        entry.configure(config);
+        entry
+    }
+
+    fn configure(&mut self, config: &Config) {
+        let name = self.widget.name();
+        match &config.widgets {
+            Some(widgets) => {
+                let position = widgets.iter().position(|w| w == name);
+                self.enabled = position.is_some();
+                self.order = position.map_or(self.widget.order(), |p| p as i32);
+            }
+            None => {
+                self.enabled = self.widget.enabled_by_default();
+                self.order = self.widget.order();
+            }
+        }
+    }
+}
+
+/// Names of the widgets `builtins` registers, which plugins can't take.
+pub const BUILTIN_NAMES: [&str; 3] = ["hud", "benchmark", "metrics"];
+
+fn builtins() -> Vec<Entry> {
+    let config = CONFIG.lock().unwrap();
+    vec![
+        Entry::new(Box::new(crate::hud::Widget), &config),
+        Entry::new(Box::new(crate::benchmark::Widget), &config),
+        Entry::new(Box::new(crate::metrics::Widget), &config),
+    ]
+}
+
+/// Adds `widget` to the overlay, enabled and ordered as the configuration says.
+///
+/// Must not be called from a widget's `ui`.
+pub fn register(widget: Box<dyn OverlayWidget>) {
+    let entry = Entry::new(widget, &CONFIG.lock().unwrap());
+    let mut registry = REGISTRY.lock().unwrap();
+    let name = entry.widget.name().to_string();
+    registry.retain(|e| e.widget.name() != name);
+    registry.push(entry);
+}
+
+/// Returns whether a widget is called `name`.
+pub fn set_enabled(name: &str, enabled: bool) -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    match registry.iter_mut().find(|e| e.widget.name() == name) {
+        Some(entry) => {
+            entry.enabled = enabled;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns whether a widget is called `name`.
+pub fn set_order(name: &str, order: i32) -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    match registry.iter_mut().find(|e| e.widget.name() == name) {
+        Some(entry) => {
+            entry.order = order;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Names of the registered widgets, with whether they are enabled, in drawing order.
+pub fn list() -> Vec<(String, bool)> {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.sort_by_key(|e| e.order);
+    registry
+        .iter()
+        .map(|e| (e.widget.name().to_string(), e.enabled))
+        .collect()
+}
+
+/// Enables and orders the widgets again after the configuration changed.
+pub(crate) fn configure(config: &Config) {
+    for entry in REGISTRY.lock().unwrap().iter_mut() {
+        entry.configure(config);
+    }
+}
+
+/// Draws the enabled widgets.
+pub(crate) fn show(ctx: &egui::Context, frame: &FrameInfo) {
+    let mut registry = REGISTRY.lock().unwrap();
+    // stable, so widgets of the same order keep the order they were registered in
+    registry.sort_by_key(|e| e.order);
+    for entry in registry.iter_mut().filter(|e| e.enabled) {
+        entry.widget.ui(ctx, frame);
+    }
+}
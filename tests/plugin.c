@@ -0,0 +1,53 @@
+// A widget plugin counting frames.
+//
+//   cc -shared -fPIC -Iinclude tests/plugin.c -o frame_counter.so
+//   mkdir -p ~/.local/share/overlib/plugins
+//   cp frame_counter.so ~/.local/share/overlib/plugins/
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "overlib_plugin.h"
+
+struct counter {
+  const OverlibApi *api;
+  unsigned long frames;
+  int paused;
+};
+
+static void *init(const OverlibApi *api) {
+  if (api->abi_version != OVERLIB_PLUGIN_ABI_VERSION)
+    return NULL;
+
+  struct counter *counter = calloc(1, sizeof(struct counter));
+  if (counter)
+    counter->api = api;
+  return counter;
+}
+
+static int draw(void *data, OverlibUi *ui, const OverlibFrame *frame) {
+  struct counter *counter = data;
+  char text[64];
+
+  if (!counter->paused)
+    counter->frames++;
+  snprintf(text, sizeof(text), "%lu frames", counter->frames);
+  counter->api->label(ui, text);
+  counter->api->checkbox(ui, "paused", &counter->paused);
+  if (counter->api->button(ui, "reset"))
+    counter->frames = 0;
+
+  return 0;
+}
+
+static void shutdown(void *data) {
+  struct counter *counter = data;
+  printf("counted %lu frames\n", counter->frames);
+  free(counter);
+}
+
+static const OverlibPlugin plugin = {
+    OVERLIB_PLUGIN_ABI_VERSION, "frame counter", init, draw, shutdown,
+};
+
+const OverlibPlugin *overlib_plugin(void) { return &plugin; }
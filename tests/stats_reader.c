@@ -0,0 +1,60 @@
+// Prints the frame statistics of every process publishing them, once a second.
+//
+//   cc -Iinclude tests/stats_reader.c -o overlib_stats
+//   OVERLIB_CONFIG_STR=shm_stats glxgears &
+//   ./overlib_stats
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#include "overlib_stats.h"
+
+_Static_assert(sizeof(OverlibStats) == 80, "layout changed");
+
+static void print(const char *name) {
+  int fd = shm_open(name, O_RDONLY, 0);
+  if (fd < 0)
+    return;
+  void *mapped = mmap(NULL, sizeof(OverlibStats), PROT_READ, MAP_SHARED, fd, 0);
+  close(fd);
+  if (mapped == MAP_FAILED)
+    return;
+
+  OverlibStats stats;
+  if (overlib_stats_read(mapped, &stats) && kill(stats.pid, 0) == 0) {
+    char gpu_time[32] = "n/a";
+    if (stats.flags & OVERLIB_STATS_HAS_GPU_TIME)
+      snprintf(gpu_time, sizeof(gpu_time), "%.2f ms", stats.gpu_time_ns / 1e6);
+    printf("%6u %-7s %5ux%-5u frame %-8lu %7.1f fps %7.2f ms gpu %s\n",
+           stats.pid, stats.frontend, stats.width, stats.height,
+           (unsigned long)stats.frame_count, stats.fps,
+           stats.frame_time_ns / 1e6, gpu_time);
+  }
+  munmap(mapped, sizeof(OverlibStats));
+}
+
+int main(void) {
+  for (;;) {
+    DIR *dir = opendir("/dev/shm");
+    if (!dir) {
+      perror("/dev/shm");
+      return 1;
+    }
+    struct dirent *entry;
+    char name[300];
+    while ((entry = readdir(dir))) {
+      if (strncmp(entry->d_name, "overlib-", 8) == 0) {
+        snprintf(name, sizeof(name), "/%s", entry->d_name);
+        print(name);
+      }
+    }
+    closedir(dir);
+    puts("");
+    sleep(1);
+  }
+}
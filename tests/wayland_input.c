@@ -0,0 +1,266 @@
+// Checks that wrapping wl_pointer and wl_keyboard listeners keeps the
+// application's handlers and user data working, with input sent through
+// weston's test protocol. Exits with 1 on any mismatch.
+//
+//   wayland-scanner client-header weston/protocol/weston-test.xml weston-test-client-protocol.h
+//   wayland-scanner private-code weston/protocol/weston-test.xml weston-test-protocol.c
+//   cc -I. tests/wayland_input.c weston-test-protocol.c -lwayland-client -o wayland_input
+//   weston --backend=headless-backend.so --socket=overlib-test --modules=test-plugin.so &
+//   WAYLAND_DISPLAY=overlib-test LD_PRELOAD=target/debug/liboverlay.so ./wayland_input
+
+#define _GNU_SOURCE
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <time.h>
+#include <unistd.h>
+#include <wayland-client.h>
+
+#include "weston-test-client-protocol.h"
+
+#define BTN_LEFT 0x110
+#define KEY_A 30
+#define SIZE 200
+
+static int user_data = 42;
+static int failures = 0;
+
+static struct {
+  int keymaps, pointer_enters, motions, presses, releases;
+  int keyboard_enters, keys_down, keys_up;
+  uint32_t button, key;
+} got;
+
+static struct wl_compositor *compositor;
+static struct wl_shm *shm;
+static struct wl_seat *seat;
+static struct weston_test *test;
+
+static void expect(int ok, const char *what) {
+  printf("%s: %s\n", what, ok ? "ok" : "FAILED");
+  if (!ok)
+    failures++;
+}
+
+static void check(void *data, const char *event) {
+  if (data != &user_data)
+    expect(0, event);
+}
+
+static void pointer_enter(void *data, struct wl_pointer *p, uint32_t serial,
+                          struct wl_surface *s, wl_fixed_t x, wl_fixed_t y) {
+  check(data, "pointer enter user data");
+  got.pointer_enters++;
+}
+static void pointer_leave(void *data, struct wl_pointer *p, uint32_t serial,
+                          struct wl_surface *s) {
+  check(data, "pointer leave user data");
+}
+static void pointer_motion(void *data, struct wl_pointer *p, uint32_t time,
+                           wl_fixed_t x, wl_fixed_t y) {
+  check(data, "pointer motion user data");
+  got.motions++;
+}
+static void pointer_button(void *data, struct wl_pointer *p, uint32_t serial,
+                           uint32_t time, uint32_t button, uint32_t state) {
+  check(data, "pointer button user data");
+  got.button = button;
+  if (state == WL_POINTER_BUTTON_STATE_PRESSED)
+    got.presses++;
+  else
+    got.releases++;
+}
+static void pointer_axis(void *data, struct wl_pointer *p, uint32_t time,
+                         uint32_t axis, wl_fixed_t value) {
+  check(data, "pointer axis user data");
+}
+
+// only the events of version 1, the seat is bound with it
+static const struct wl_pointer_listener pointer_listener = {
+    pointer_enter, pointer_leave, pointer_motion, pointer_button, pointer_axis,
+};
+
+static void keyboard_keymap(void *data, struct wl_keyboard *k, uint32_t format,
+                            int fd, uint32_t size) {
+  check(data, "keyboard keymap user data");
+  got.keymaps++;
+  close(fd);
+}
+static void keyboard_enter(void *data, struct wl_keyboard *k, uint32_t serial,
+                           struct wl_surface *s, struct wl_array *keys) {
+  check(data, "keyboard enter user data");
+  got.keyboard_enters++;
+}
+static void keyboard_leave(void *data, struct wl_keyboard *k, uint32_t serial,
+                           struct wl_surface *s) {
+  check(data, "keyboard leave user data");
+}
+static void keyboard_key(void *data, struct wl_keyboard *k, uint32_t serial,
+                         uint32_t time, uint32_t key, uint32_t state) {
+  check(data, "keyboard key user data");
+  got.key = key;
+  if (state == WL_KEYBOARD_KEY_STATE_PRESSED)
+    got.keys_down++;
+  else
+    got.keys_up++;
+}
+static void keyboard_modifiers(void *data, struct wl_keyboard *k,
+                               uint32_t serial, uint32_t depressed,
+                               uint32_t latched, uint32_t locked,
+                               uint32_t group) {
+  check(data, "keyboard modifiers user data");
+}
+
+static const struct wl_keyboard_listener keyboard_listener = {
+    keyboard_keymap, keyboard_enter,     keyboard_leave,
+    keyboard_key,    keyboard_modifiers,
+};
+
+static void seat_capabilities(void *data, struct wl_seat *seat, uint32_t caps) {
+  if (caps & WL_SEAT_CAPABILITY_POINTER) {
+    struct wl_pointer *pointer = wl_seat_get_pointer(seat);
+    wl_pointer_add_listener(pointer, &pointer_listener, &user_data);
+    expect(wl_pointer_get_user_data(pointer) == &user_data,
+           "pointer user data");
+  }
+  if (caps & WL_SEAT_CAPABILITY_KEYBOARD) {
+    struct wl_keyboard *keyboard = wl_seat_get_keyboard(seat);
+    wl_keyboard_add_listener(keyboard, &keyboard_listener, &user_data);
+    expect(wl_keyboard_get_user_data(keyboard) == &user_data,
+           "keyboard user data");
+  }
+}
+
+static const struct wl_seat_listener seat_listener = {seat_capabilities};
+
+static void global(void *data, struct wl_registry *registry, uint32_t name,
+                   const char *interface, uint32_t version) {
+  if (strcmp(interface, "wl_compositor") == 0) {
+    compositor = wl_registry_bind(registry, name, &wl_compositor_interface, 1);
+  } else if (strcmp(interface, "wl_shm") == 0) {
+    shm = wl_registry_bind(registry, name, &wl_shm_interface, 1);
+  } else if (strcmp(interface, "wl_seat") == 0) {
+    seat = wl_registry_bind(registry, name, &wl_seat_interface, 1);
+    wl_seat_add_listener(seat, &seat_listener, NULL);
+  } else if (strcmp(interface, "weston_test") == 0) {
+    test = wl_registry_bind(registry, name, &weston_test_interface, 1);
+  }
+}
+
+static void global_remove(void *data, struct wl_registry *registry,
+                          uint32_t name) {}
+
+static const struct wl_registry_listener registry_listener = {global,
+                                                              global_remove};
+
+// a white square for the surface to be mapped and get input
+static struct wl_buffer *create_buffer(void) {
+  int stride = SIZE * 4;
+  int fd = memfd_create("wayland_input", MFD_CLOEXEC);
+  if (fd < 0 || ftruncate(fd, stride * SIZE) < 0)
+    return NULL;
+  void *pixels =
+      mmap(NULL, stride * SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+  if (pixels == MAP_FAILED) {
+    close(fd);
+    return NULL;
+  }
+  memset(pixels, 0xff, stride * SIZE);
+  munmap(pixels, stride * SIZE);
+
+  struct wl_shm_pool *pool = wl_shm_create_pool(shm, fd, stride * SIZE);
+  struct wl_buffer *buffer = wl_shm_pool_create_buffer(
+      pool, 0, SIZE, SIZE, stride, WL_SHM_FORMAT_XRGB8888);
+  wl_shm_pool_destroy(pool);
+  close(fd);
+  return buffer;
+}
+
+// the test protocol's timestamp arguments
+static void now(uint32_t *sec_hi, uint32_t *sec_lo, uint32_t *nsec) {
+  struct timespec ts;
+  clock_gettime(CLOCK_MONOTONIC, &ts);
+  *sec_hi = (uint64_t)ts.tv_sec >> 32;
+  *sec_lo = (uint32_t)ts.tv_sec;
+  *nsec = ts.tv_nsec;
+}
+
+static void move_pointer(int x, int y) {
+  uint32_t hi, lo, nsec;
+  now(&hi, &lo, &nsec);
+  weston_test_move_pointer(test, hi, lo, nsec, x, y);
+}
+
+static void send_button(uint32_t button, uint32_t state) {
+  uint32_t hi, lo, nsec;
+  now(&hi, &lo, &nsec);
+  weston_test_send_button(test, hi, lo, nsec, button, state);
+}
+
+static void send_key(uint32_t key, uint32_t state) {
+  uint32_t hi, lo, nsec;
+  now(&hi, &lo, &nsec);
+  weston_test_send_key(test, hi, lo, nsec, key, state);
+}
+
+int main(int argc, char **argv) {
+  struct wl_display *display = wl_display_connect(NULL);
+  if (!display) {
+    printf("could not connect to the compositor\n");
+    return 1;
+  }
+
+  wl_registry_add_listener(wl_display_get_registry(display), &registry_listener,
+                           NULL);
+  // globals, then seat capabilities, then the keymap
+  for (int i = 0; i < 3; i++)
+    wl_display_roundtrip(display);
+
+  if (!compositor || !shm || !seat || !test) {
+    printf("missing globals, is weston running with test-plugin.so?\n");
+    return 1;
+  }
+  expect(got.keymaps == 1, "keymap");
+
+  struct wl_buffer *buffer = create_buffer();
+  if (!buffer) {
+    printf("could not create a buffer\n");
+    return 1;
+  }
+  struct wl_surface *surface = wl_compositor_create_surface(compositor);
+  weston_test_move_surface(test, surface, 0, 0);
+  wl_surface_attach(surface, buffer, 0, 0);
+  wl_surface_damage(surface, 0, 0, SIZE, SIZE);
+  wl_surface_commit(surface);
+  wl_display_roundtrip(display);
+
+  weston_test_activate_surface(test, surface);
+  wl_display_roundtrip(display);
+  expect(got.keyboard_enters == 1, "keyboard enter");
+
+  move_pointer(SIZE / 2, SIZE / 2);
+  wl_display_roundtrip(display);
+  move_pointer(SIZE / 2 + 10, SIZE / 2 + 10);
+  wl_display_roundtrip(display);
+  expect(got.pointer_enters == 1, "pointer enter");
+  expect(got.motions >= 1, "pointer motion");
+
+  send_button(BTN_LEFT, WL_POINTER_BUTTON_STATE_PRESSED);
+  send_button(BTN_LEFT, WL_POINTER_BUTTON_STATE_RELEASED);
+  wl_display_roundtrip(display);
+  expect(got.presses == 1 && got.releases == 1 && got.button == BTN_LEFT,
+         "button press and release");
+
+  send_key(KEY_A, WL_KEYBOARD_KEY_STATE_PRESSED);
+  send_key(KEY_A, WL_KEYBOARD_KEY_STATE_RELEASED);
+  wl_display_roundtrip(display);
+  expect(got.keys_down == 1 && got.keys_up == 1 && got.key == KEY_A,
+         "key press and release");
+
+  wl_surface_destroy(surface);
+  wl_buffer_destroy(buffer);
+  wl_display_disconnect(display);
+
+  printf("%d failures\n", failures);
+  return failures ? 1 : 0;
+}